@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use crate::storage::unix_time_millis;
+
+/// Denials of the same kind within this window are folded into one entry.
+const GROUPING_WINDOW_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AclLogReason {
+    Auth,
+    Command,
+    Key,
+}
+
+impl AclLogReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AclLogReason::Auth => "auth",
+            AclLogReason::Command => "command",
+            AclLogReason::Key => "key",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AclLogEntry {
+    pub id: u64,
+    pub count: u64,
+    pub reason: AclLogReason,
+    /// The command or key that was refused, or `AUTH` for failed authentications.
+    pub object: String,
+    pub username: String,
+    pub client_info: String,
+    pub created: u64,
+    pub last_updated: u64,
+}
+
+/// Bounded log of recent authentication failures and permission denials, newest first.
+#[derive(Debug)]
+pub struct AclLog {
+    entries: VecDeque<AclLogEntry>,
+    next_id: u64,
+    max_len: usize,
+}
+
+impl AclLog {
+    pub fn new(max_len: u64) -> Self {
+        AclLog { entries: VecDeque::new(), next_id: 0, max_len: max_len as usize }
+    }
+
+    pub fn configure(&mut self, max_len: u64) {
+        self.max_len = max_len as usize;
+        self.entries.truncate(self.max_len);
+    }
+
+    pub fn record(&mut self, reason: AclLogReason, object: &str, username: &str, client_info: &str) {
+        let now = unix_time_millis();
+        let similar = self.entries.iter().position(|entry| {
+            entry.reason == reason
+                && entry.object == object
+                && entry.username == username
+                && now.saturating_sub(entry.last_updated) < GROUPING_WINDOW_MS
+        });
+
+        let entry = match similar.and_then(|index| self.entries.remove(index)) {
+            Some(mut entry) => {
+                entry.count += 1;
+                entry.last_updated = now;
+                entry.client_info = client_info.to_string();
+                entry
+            }
+            None => {
+                self.next_id += 1;
+                AclLogEntry {
+                    id: self.next_id - 1,
+                    count: 1,
+                    reason,
+                    object: object.to_string(),
+                    username: username.to_string(),
+                    client_info: client_info.to_string(),
+                    created: now,
+                    last_updated: now,
+                }
+            }
+        };
+        self.entries.push_front(entry);
+        self.entries.truncate(self.max_len);
+    }
+
+    /// Up to `count` entries, newest first. `None` returns all of them.
+    pub fn get(&self, count: Option<usize>) -> impl Iterator<Item = &AclLogEntry> {
+        self.entries.iter().take(count.unwrap_or(usize::MAX))
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,159 @@
+pub mod log;
+pub mod selector;
+pub mod user;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::acl::log::AclLog;
+use crate::acl::selector::Denial;
+use crate::acl::user::{User, DEFAULT_USER};
+use crate::commands::table::CommandSpec;
+use crate::config::config_file::split_args;
+use crate::config::server_config::ServerConfig;
+use crate::errors::app_errors::AppError;
+use crate::utils::atomic_file::write_atomically;
+
+/// Every user, the ACL log and where users are persisted.
+#[derive(Debug)]
+pub struct Acl {
+    users: BTreeMap<String, User>,
+    pub(crate) log: AclLog,
+    /// The `requirepass` last applied to the default user, so other config changes leave it alone.
+    requirepass: Option<String>,
+    file: Option<PathBuf>,
+}
+
+impl Acl {
+    pub fn new(config: &ServerConfig) -> Self {
+        let mut users = BTreeMap::new();
+        users.insert(DEFAULT_USER.to_string(), default_user(&config.requirepass));
+        Acl {
+            users,
+            log: AclLog::new(config.acllog_max_len),
+            requirepass: config.requirepass.clone(),
+            file: config.aclfile.as_ref().map(PathBuf::from),
+        }
+    }
+
+    /// Picks up settings changed through CONFIG SET. A new `requirepass` replaces the default
+    /// user's passwords, an empty one makes it `nopass` again.
+    pub fn apply_config(&mut self, config: &ServerConfig) {
+        self.log.configure(config.acllog_max_len);
+        if config.requirepass == self.requirepass {
+            return;
+        }
+        if let Some(user) = self.users.get_mut(DEFAULT_USER) {
+            let rules = match &config.requirepass {
+                Some(password) => vec!["resetpass".to_string(), format!(">{}", password)],
+                None => vec!["nopass".to_string()],
+            };
+            // These rules are always valid
+            let _ = user.apply_rules(&rules);
+        }
+        self.requirepass = config.requirepass.clone();
+    }
+
+    pub fn get(&self, name: &str) -> Option<&User> {
+        self.users.get(name)
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = &User> {
+        self.users.values()
+    }
+
+    /// Creates or modifies a user. Rules are applied to a copy, so an invalid rule changes nothing.
+    pub fn set_user(&mut self, name: &str, rules: &[String]) -> Result<(), AppError> {
+        if name.chars().any(|c| c.is_whitespace() || c == '\0') {
+            return Err(AppError::InvalidUsername);
+        }
+        let mut user = self.users.get(name).cloned().unwrap_or_else(|| User::new(name));
+        user.apply_rules(rules).map_err(|(rule, reason)| AppError::AclSetUser(rule, reason))?;
+        self.users.insert(name.to_string(), user);
+        Ok(())
+    }
+
+    /// Deletes users, returning the names that existed. The default user cannot be deleted.
+    pub fn delete_users(&mut self, names: &[String]) -> Result<Vec<String>, AppError> {
+        if names.iter().any(|name| name == DEFAULT_USER) {
+            return Err(AppError::DefaultUserRemoval);
+        }
+        Ok(names.iter().filter(|name| self.users.remove(name.as_str()).is_some()).cloned().collect())
+    }
+
+    /// Whether connections have to authenticate, which is the case unless the default user is
+    /// enabled and accepts any password.
+    pub fn auth_required(&self) -> bool {
+        self.users.get(DEFAULT_USER).is_none_or(|user| !user.enabled || !user.nopass)
+    }
+
+    pub fn authenticate(&self, username: &str, password: &str) -> bool {
+        self.users.get(username).is_some_and(|user| user.check_password(password))
+    }
+
+    pub fn check(&self, username: &str, spec: &CommandSpec, args: &[String]) -> Result<(), Denial> {
+        match self.users.get(username) {
+            Some(user) => user.check(spec, args),
+            None => Err(Denial::Command),
+        }
+    }
+
+    /// Replaces every user with the ones in the ACL file. Nothing changes if the file is invalid.
+    pub fn load(&mut self) -> Result<(), AppError> {
+        let path = self.file.as_ref().ok_or(AppError::NoAclFile)?;
+        let mut users = read_acl_file(path)?;
+        users.entry(DEFAULT_USER.to_string()).or_insert_with(|| default_user(&self.requirepass));
+        self.users = users;
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        let path = self.file.as_ref().ok_or(AppError::NoAclFile)?;
+        write_atomically(path, |writer| {
+            for user in self.users.values() {
+                writeln!(writer, "{}", user.describe()).map_err(AppError::FileError)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+fn default_user(requirepass: &Option<String>) -> User {
+    let mut user = User::default_user();
+    if let Some(password) = requirepass {
+        // These rules are always valid
+        let _ = user.apply_rules(&["resetpass".to_string(), format!(">{}", password)]);
+    }
+    user
+}
+
+/// Parses an ACL file made of `user <name> <rules...>` lines, as written by ACL SAVE.
+fn read_acl_file(path: &Path) -> Result<BTreeMap<String, User>, AppError> {
+    let source = path.display().to_string();
+    let content = fs::read_to_string(path).map_err(AppError::FileError)?;
+    let mut users = BTreeMap::new();
+
+    for (index, raw) in content.lines().enumerate() {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let error = |reason: String| AppError::AclFileError(source.clone(), index + 1, reason);
+        let args = split_args(trimmed).ok_or_else(|| error("Unbalanced quotes in configuration line".to_string()))?;
+        if args.len() < 2 || !args[0].eq_ignore_ascii_case("user") {
+            return Err(error("line should start with user keyword".to_string()));
+        }
+        let name = &args[1];
+        if users.contains_key(name) {
+            return Err(error(format!("Duplicate user '{}' found", name)));
+        }
+
+        let mut user = User::new(name);
+        user.apply_rules(&args[2..])
+            .map_err(|(rule, reason)| error(format!("Error in applying operation '{}': {}", rule, reason)))?;
+        users.insert(name.clone(), user);
+    }
+    Ok(users)
+}
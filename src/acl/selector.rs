@@ -0,0 +1,257 @@
+use crate::commands::table::{find_command, CommandSpec, ACL_CATEGORIES, KEY_READ, KEY_WRITE};
+use crate::utils::glob::glob_match;
+
+/// A key pattern together with the access it grants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyPattern {
+    pub pattern: String,
+    pub access: u8,
+}
+
+impl KeyPattern {
+    fn describe(&self) -> String {
+        match self.access {
+            KEY_READ => format!("%R~{}", self.pattern),
+            KEY_WRITE => format!("%W~{}", self.pattern),
+            _ => format!("~{}", self.pattern),
+        }
+    }
+}
+
+/// Why a selector refused a command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Denial {
+    Command,
+    Key(String),
+}
+
+/// A set of command, key and channel permissions. Every user has a root selector and may have
+/// additional ones; a command is allowed when any of them allows it entirely.
+#[derive(Debug, Clone)]
+pub struct Selector {
+    /// Command rules in the order they were applied, e.g. `+@read -keys +config|get`.
+    /// The last rule matching a command decides whether it is allowed.
+    commands: Vec<String>,
+    keys: Vec<KeyPattern>,
+    channels: Vec<String>,
+}
+
+impl Selector {
+    /// A selector that allows nothing.
+    pub fn new() -> Self {
+        Selector { commands: vec!["-@all".to_string()], keys: Vec::new(), channels: Vec::new() }
+    }
+
+    /// Applies a single command, key or channel rule. Returns a reason on an invalid rule.
+    pub fn apply(&mut self, rule: &str) -> Result<(), String> {
+        let lowercase = rule.to_lowercase();
+        match lowercase.as_str() {
+            "allcommands" | "+@all" => self.commands = vec!["+@all".to_string()],
+            "nocommands" | "-@all" => self.commands = vec!["-@all".to_string()],
+            "allkeys" => self.keys = vec![KeyPattern { pattern: "*".to_string(), access: KEY_READ | KEY_WRITE }],
+            "resetkeys" => self.keys.clear(),
+            "allchannels" => self.channels = vec!["*".to_string()],
+            "resetchannels" => self.channels.clear(),
+            _ if rule.starts_with('~') => self.add_key_pattern(&rule[1..], KEY_READ | KEY_WRITE),
+            _ if rule.starts_with('%') => {
+                let (permissions, pattern) = rule[1..].split_once('~').ok_or("Syntax error")?;
+                let mut access = 0;
+                for c in permissions.chars() {
+                    access |= match c.to_ascii_uppercase() {
+                        'R' => KEY_READ,
+                        'W' => KEY_WRITE,
+                        _ => return Err("Syntax error".to_string()),
+                    };
+                }
+                if access == 0 {
+                    return Err("Syntax error".to_string());
+                }
+                self.add_key_pattern(pattern, access);
+            }
+            _ if rule.starts_with('&') => {
+                if !self.channels.iter().any(|channel| channel == "*" || channel == &rule[1..]) {
+                    self.channels.push(rule[1..].to_string());
+                }
+            }
+            _ if rule.starts_with('+') || rule.starts_with('-') => {
+                let target = &lowercase[1..];
+                let known = match target.strip_prefix('@') {
+                    Some(category) => ACL_CATEGORIES.contains(&category),
+                    None => find_command(target.split('|').next().unwrap_or_default()).is_some(),
+                };
+                if !known {
+                    return Err("Unknown command or category name in ACL".to_string());
+                }
+                self.commands.retain(|existing| existing[1..] != *target);
+                self.commands.push(lowercase);
+            }
+            _ => return Err("Syntax error".to_string()),
+        }
+        Ok(())
+    }
+
+    fn add_key_pattern(&mut self, pattern: &str, access: u8) {
+        match self.keys.iter_mut().find(|key| key.pattern == pattern) {
+            Some(key) => key.access |= access,
+            None => self.keys.push(KeyPattern { pattern: pattern.to_string(), access }),
+        }
+    }
+
+    fn allows_command(&self, spec: &CommandSpec, args: &[String]) -> bool {
+        let mut allowed = false;
+        for rule in &self.commands {
+            let target = &rule[1..];
+            let matches = match target.strip_prefix('@') {
+                Some("all") => true,
+                Some(category) => spec.in_category(category),
+                None => match target.split_once('|') {
+                    Some((command, subcommand)) => {
+                        command == spec.name && args.first().is_some_and(|arg| arg.eq_ignore_ascii_case(subcommand))
+                    }
+                    None => target == spec.name,
+                },
+            };
+            if matches {
+                allowed = rule.starts_with('+');
+            }
+        }
+        allowed
+    }
+
+    /// Checks a call against this selector. `args` excludes the command name.
+    pub fn check(&self, spec: &CommandSpec, args: &[String]) -> Result<(), Denial> {
+        if !self.allows_command(spec, args) {
+            return Err(Denial::Command);
+        }
+        for (key, access) in spec.key_args(args) {
+            let permitted = self
+                .keys
+                .iter()
+                .any(|pattern| pattern.access & access == access && glob_match(&pattern.pattern, key, false));
+            if !permitted {
+                return Err(Denial::Key(key.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn describe_commands(&self) -> String {
+        self.commands.join(" ")
+    }
+
+    pub fn describe_keys(&self) -> String {
+        self.keys.iter().map(KeyPattern::describe).collect::<Vec<String>>().join(" ")
+    }
+
+    pub fn describe_channels(&self) -> String {
+        self.channels.iter().map(|channel| format!("&{}", channel)).collect::<Vec<String>>().join(" ")
+    }
+
+    /// The selector as rules that recreate it, e.g. `~cache:* resetchannels +@read`.
+    pub fn describe(&self) -> String {
+        let mut rules = Vec::new();
+        if !self.keys.is_empty() {
+            rules.push(self.describe_keys());
+        }
+        rules.push(match self.channels.is_empty() {
+            true => "resetchannels".to_string(),
+            false => self.describe_channels(),
+        });
+        rules.push(self.describe_commands());
+        rules.join(" ")
+    }
+}
+
+impl Default for Selector {
+    fn default() -> Self {
+        Selector::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_rules(rules: &[&str]) -> Selector {
+        let mut selector = Selector::new();
+        for rule in rules {
+            selector.apply(rule).unwrap();
+        }
+        selector
+    }
+
+    fn check(selector: &Selector, command: &str, args: &[&str]) -> Result<(), Denial> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        selector.check(find_command(command).unwrap(), &args)
+    }
+
+    #[test]
+    fn starts_out_allowing_nothing() {
+        let selector = Selector::new();
+        assert_eq!(check(&selector, "ping", &[]), Err(Denial::Command));
+        assert_eq!(selector.describe(), "resetchannels -@all");
+    }
+
+    #[test]
+    fn applies_command_rules_in_order() {
+        let selector = with_rules(&["+@all", "-@write", "+set", "-config|set"]);
+        assert_eq!(check(&selector, "ping", &[]), Ok(()));
+        assert_eq!(check(&selector, "del", &[]), Err(Denial::Command));
+        assert_eq!(check(&selector, "config", &["GET", "port"]), Ok(()));
+        assert_eq!(check(&selector, "config", &["set", "port", "1"]), Err(Denial::Command));
+        assert_eq!(selector.describe_commands(), "+@all -@write +set -config|set");
+
+        // Re-adding a rule moves it to the end instead of duplicating it
+        let selector = with_rules(&["+get", "-@all", "+get"]);
+        assert_eq!(selector.describe_commands(), "-@all +get");
+        assert_eq!(with_rules(&["+@read", "nocommands"]).describe_commands(), "-@all");
+        assert_eq!(with_rules(&["-@all", "allcommands"]).describe_commands(), "+@all");
+    }
+
+    #[test]
+    fn checks_keys_against_their_access() {
+        let selector = with_rules(&["+@all", "~cache:*", "%R~config:*", "%W~log:*"]);
+        assert_eq!(check(&selector, "set", &["cache:a", "1"]), Ok(()));
+        assert_eq!(check(&selector, "get", &["config:port"]), Ok(()));
+        assert_eq!(check(&selector, "set", &["config:port", "1"]), Err(Denial::Key("config:port".to_string())));
+        assert_eq!(check(&selector, "get", &["log:1"]), Err(Denial::Key("log:1".to_string())));
+        assert_eq!(check(&selector, "del", &["cache:a", "other"]), Err(Denial::Key("other".to_string())));
+        assert_eq!(selector.describe_keys(), "~cache:* %R~config:* %W~log:*");
+
+        // Granting the missing access to a pattern merges it
+        let selector = with_rules(&["%R~a", "%w~a", "%RW~b"]);
+        assert_eq!(selector.describe_keys(), "~a ~b");
+        assert_eq!(with_rules(&["~a", "allkeys"]).describe_keys(), "~*");
+        assert_eq!(with_rules(&["allkeys", "resetkeys"]).describe_keys(), "");
+    }
+
+    #[test]
+    fn collects_channels() {
+        assert_eq!(with_rules(&["&news", "&sport", "&news"]).describe_channels(), "&news &sport");
+        assert_eq!(with_rules(&["allchannels", "&news"]).describe_channels(), "&*");
+        assert_eq!(with_rules(&["allchannels", "resetchannels"]).describe_channels(), "");
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let mut selector = Selector::new();
+        assert_eq!(selector.apply("+nosuchcommand"), Err("Unknown command or category name in ACL".to_string()));
+        assert_eq!(selector.apply("-@nosuchcategory"), Err("Unknown command or category name in ACL".to_string()));
+        assert_eq!(selector.apply("%X~key"), Err("Syntax error".to_string()));
+        assert_eq!(selector.apply("%~key"), Err("Syntax error".to_string()));
+        assert_eq!(selector.apply("%R"), Err("Syntax error".to_string()));
+        assert_eq!(selector.apply("bogus"), Err("Syntax error".to_string()));
+        assert_eq!(selector.describe(), "resetchannels -@all");
+    }
+
+    #[test]
+    fn describes_rules_that_recreate_it() {
+        let original = with_rules(&["%R~cache:*", "~session:*", "&news", "+@read", "-keys", "+config|get"]);
+        let description = original.describe();
+        assert_eq!(description, "%R~cache:* ~session:* &news -@all +@read -keys +config|get");
+
+        let rebuilt = with_rules(&description.split(' ').collect::<Vec<&str>>());
+        assert_eq!(rebuilt.describe(), description);
+        assert_eq!(rebuilt.keys, original.keys);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::acl::selector::{Denial, Selector};
+use crate::commands::table::CommandSpec;
+use crate::utils::constant_time::constant_time_eq;
+use crate::utils::sha256::sha256_hex;
+
+/// The user connections start as, and the one `requirepass` sets the password of.
+pub const DEFAULT_USER: &str = "default";
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    pub enabled: bool,
+    /// Any password is accepted.
+    pub nopass: bool,
+    /// SHA-256 digests of the accepted passwords, as lowercase hex.
+    pub passwords: Vec<String>,
+    pub root: Selector,
+    pub selectors: Vec<Selector>,
+}
+
+impl User {
+    /// A new user is disabled and can do nothing until rules are applied.
+    pub fn new(name: &str) -> Self {
+        User {
+            name: name.to_string(),
+            enabled: false,
+            nopass: false,
+            passwords: Vec::new(),
+            root: Selector::new(),
+            selectors: Vec::new(),
+        }
+    }
+
+    /// The `default` user as it exists before any configuration: `on nopass ~* &* +@all`.
+    pub fn default_user() -> Self {
+        let mut user = User::new(DEFAULT_USER);
+        for rule in ["on", "nopass", "allkeys", "allchannels", "allcommands"] {
+            // These rules are always valid
+            let _ = user.apply(rule);
+        }
+        user
+    }
+
+    /// Applies rules in order, stopping at the first invalid one. Selectors split over several
+    /// arguments, as in `(~cache:*` `+get)`, are joined back together first.
+    pub fn apply_rules(&mut self, rules: &[String]) -> Result<(), (String, String)> {
+        let mut merged: Vec<String> = Vec::new();
+        let mut open: Option<String> = None;
+        for rule in rules {
+            match open.take() {
+                Some(mut selector) => {
+                    selector.push(' ');
+                    selector.push_str(rule);
+                    if rule.ends_with(')') {
+                        merged.push(selector);
+                    } else {
+                        open = Some(selector);
+                    }
+                }
+                None if rule.starts_with('(') && !rule.ends_with(')') => open = Some(rule.clone()),
+                None => merged.push(rule.clone()),
+            }
+        }
+        if let Some(selector) = open {
+            return Err((selector, "Unmatched parenthesis in acl selector starting at '('.".to_string()));
+        }
+
+        for rule in merged {
+            self.apply(&rule).map_err(|reason| (rule.clone(), reason))?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, rule: &str) -> Result<(), String> {
+        match rule.to_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "clearselectors" => self.selectors.clear(),
+            "reset" => *self = User::new(&self.name),
+            _ if rule.starts_with('>') => self.add_password(sha256_hex(&rule.as_bytes()[1..])),
+            _ if rule.starts_with('#') => {
+                let hash = &rule[1..];
+                if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)) {
+                    return Err("The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters".to_string());
+                }
+                self.add_password(hash.to_string());
+            }
+            _ if rule.starts_with('<') => self.remove_password(&sha256_hex(&rule.as_bytes()[1..]))?,
+            _ if rule.starts_with('!') => self.remove_password(&rule[1..])?,
+            _ if rule.starts_with('(') && rule.ends_with(')') => {
+                let mut selector = Selector::new();
+                for selector_rule in rule[1..rule.len() - 1].split_whitespace() {
+                    selector.apply(selector_rule)?;
+                }
+                self.selectors.push(selector);
+            }
+            _ => self.root.apply(rule)?,
+        }
+        Ok(())
+    }
+
+    fn add_password(&mut self, hash: String) {
+        self.nopass = false;
+        if !self.passwords.contains(&hash) {
+            self.passwords.push(hash);
+        }
+    }
+
+    fn remove_password(&mut self, hash: &str) -> Result<(), String> {
+        let count = self.passwords.len();
+        self.passwords.retain(|existing| existing != hash);
+        match self.passwords.len() < count {
+            true => Ok(()),
+            false => Err("no such password".to_string()),
+        }
+    }
+
+    /// Whether `password` opens this user. Hashes are compared in constant time.
+    pub fn check_password(&self, password: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.nopass {
+            return true;
+        }
+        let hash = sha256_hex(password.as_bytes());
+        // Every stored hash is compared so the time taken does not reveal which one matched
+        self.passwords
+            .iter()
+            .fold(false, |matched, stored| constant_time_eq(stored.as_bytes(), hash.as_bytes()) | matched)
+    }
+
+    /// Checks a call against the root selector and then every other selector. When all refuse,
+    /// a key denial is preferred over a command one since it is the more specific reason.
+    pub fn check(&self, spec: &CommandSpec, args: &[String]) -> Result<(), Denial> {
+        let mut denial = Denial::Command;
+        for selector in std::iter::once(&self.root).chain(&self.selectors) {
+            match selector.check(spec, args) {
+                Ok(()) => return Ok(()),
+                Err(Denial::Key(key)) if denial == Denial::Command => denial = Denial::Key(key),
+                Err(_) => {}
+            }
+        }
+        Err(denial)
+    }
+
+    pub fn flags(&self) -> Vec<String> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }.to_string()];
+        if self.nopass {
+            flags.push("nopass".to_string());
+        }
+        flags
+    }
+
+    /// The user as an ACL LIST line, which is also the format of the ACL file.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("user {}", self.name)];
+        parts.extend(self.flags());
+        parts.extend(self.passwords.iter().map(|hash| format!("#{}", hash)));
+        parts.push(self.root.describe());
+        parts.extend(self.selectors.iter().map(|selector| format!("({})", selector.describe())));
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::table::find_command;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn user(rules: &[&str]) -> User {
+        let mut user = User::new("alice");
+        user.apply_rules(&strings(rules)).unwrap();
+        user
+    }
+
+    fn check(user: &User, command: &str, args: &[&str]) -> Result<(), Denial> {
+        user.check(find_command(command).unwrap(), &strings(args))
+    }
+
+    #[test]
+    fn switches_on_and_off() {
+        let mut alice = user(&["nopass"]);
+        assert!(!alice.check_password("anything"));
+        alice.apply_rules(&strings(&["on"])).unwrap();
+        assert!(alice.check_password("anything"));
+        alice.apply_rules(&strings(&["off"])).unwrap();
+        assert!(!alice.check_password("anything"));
+        assert_eq!(alice.flags(), ["off", "nopass"]);
+    }
+
+    #[test]
+    fn manages_passwords() {
+        let mut alice = user(&["on", ">secret", ">other", ">secret"]);
+        assert_eq!(alice.passwords.len(), 2);
+        assert!(alice.check_password("secret"));
+        assert!(alice.check_password("other"));
+        assert!(!alice.check_password("wrong"));
+
+        alice.apply_rules(&strings(&["<other"])).unwrap();
+        assert!(!alice.check_password("other"));
+        assert_eq!(alice.apply_rules(&strings(&["<other"])), Err(("<other".to_string(), "no such password".to_string())));
+
+        let hash = sha256_hex(b"hashed");
+        alice.apply_rules(&[format!("#{}", hash)]).unwrap();
+        assert!(alice.check_password("hashed"));
+        alice.apply_rules(&[format!("!{}", hash)]).unwrap();
+        assert!(!alice.check_password("hashed"));
+        assert!(alice.apply_rules(&strings(&["#ABC"])).is_err());
+        assert!(alice.apply_rules(&[format!("#{}", hash.to_uppercase())]).is_err());
+
+        alice.apply_rules(&strings(&["nopass"])).unwrap();
+        assert!(alice.passwords.is_empty());
+        assert!(alice.check_password("wrong"));
+        // Adding a password takes nopass away again
+        alice.apply_rules(&strings(&[">secret"])).unwrap();
+        assert!(!alice.check_password("wrong"));
+
+        alice.apply_rules(&strings(&["resetpass"])).unwrap();
+        assert!(!alice.nopass);
+        assert!(!alice.check_password("secret"));
+    }
+
+    #[test]
+    fn allows_a_call_when_any_selector_does() {
+        let alice = user(&["on", "nopass", "~cache:*", "+get", "(%R~config:* +@read)", "(~log:* +set)"]);
+        assert_eq!(alice.selectors.len(), 2);
+        assert_eq!(check(&alice, "get", &["cache:a"]), Ok(()));
+        assert_eq!(check(&alice, "get", &["config:port"]), Ok(()));
+        assert_eq!(check(&alice, "set", &["log:1", "x"]), Ok(()));
+        assert_eq!(check(&alice, "set", &["cache:a", "x"]), Err(Denial::Key("cache:a".to_string())));
+        assert_eq!(check(&alice, "del", &["cache:a"]), Err(Denial::Command));
+
+        let mut alice = alice;
+        alice.apply_rules(&strings(&["clearselectors"])).unwrap();
+        assert!(alice.selectors.is_empty());
+        assert_eq!(check(&alice, "set", &["log:1", "x"]), Err(Denial::Command));
+    }
+
+    #[test]
+    fn joins_selectors_split_over_arguments() {
+        let alice = user(&["(~cache:*", "&news", "+get)", "allchannels"]);
+        assert_eq!(alice.selectors[0].describe(), "~cache:* &news -@all +get");
+        assert_eq!(alice.root.describe_channels(), "&*");
+
+        let mut bob = User::new("bob");
+        let unmatched = bob.apply_rules(&strings(&["on", "(~cache:*", "+get"]));
+        assert_eq!(unmatched.unwrap_err().1, "Unmatched parenthesis in acl selector starting at '('.");
+        // Nothing is applied when the selectors do not parse
+        assert!(!bob.enabled);
+    }
+
+    #[test]
+    fn stops_at_the_first_invalid_rule() {
+        let mut alice = User::new("alice");
+        let error = alice.apply_rules(&strings(&["on", "+nosuchcommand", "nopass"]));
+        assert_eq!(error, Err(("+nosuchcommand".to_string(), "Unknown command or category name in ACL".to_string())));
+        assert!(alice.enabled);
+        assert!(!alice.nopass);
+        assert!(alice.apply_rules(&strings(&["(+bogus)"])).is_err());
+    }
+
+    #[test]
+    fn resets_to_a_new_user() {
+        let mut alice = user(&["on", ">secret", "allkeys", "allchannels", "+@all", "(+get)"]);
+        alice.apply_rules(&strings(&["reset"])).unwrap();
+        assert_eq!(alice.name, "alice");
+        assert_eq!(alice.describe(), User::new("alice").describe());
+        assert_eq!(alice.describe(), "user alice off resetchannels -@all");
+    }
+
+    #[test]
+    fn describes_the_default_user() {
+        assert_eq!(User::default_user().describe(), "user default on nopass ~* &* +@all");
+    }
+
+    #[test]
+    fn survives_a_describe_and_parse_round_trip() {
+        let hash = sha256_hex(b"secret");
+        let original = user(&["on", ">secret", "%R~cache:*", "&news", "+@read", "-keys", "(~log:* resetchannels +set)", "(+ping)"]);
+        let description = original.describe();
+        assert_eq!(
+            description,
+            format!("user alice on #{} %R~cache:* &news -@all +@read -keys (~log:* resetchannels -@all +set) (resetchannels -@all +ping)", hash)
+        );
+
+        // ACL SAVE writes this line and ACL LOAD applies everything after the name
+        let args: Vec<&str> = description.split(' ').collect();
+        let rebuilt = user(&args[2..]);
+        assert_eq!(rebuilt.describe(), description);
+        assert!(rebuilt.check_password("secret"));
+        assert_eq!(check(&rebuilt, "set", &["log:1", "x"]), Ok(()));
+
+        let mut default = User::new(DEFAULT_USER);
+        let description = User::default_user().describe();
+        default.apply_rules(&strings(&description.split(' ').collect::<Vec<&str>>()[2..])).unwrap();
+        assert_eq!(default.describe(), description);
+    }
+}
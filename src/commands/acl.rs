@@ -0,0 +1,163 @@
+use crate::acl::selector::{Denial, Selector};
+use crate::acl::user::User;
+use crate::commands::table::{find_command, ACL_CATEGORIES, COMMANDS};
+use crate::config::info_server::InfoServer;
+use crate::errors::app_errors::AppError;
+use crate::resp::parser::Parser;
+use crate::resp::parser::Parser::{Array, BulkString, Integer, NestedArray, NullBulkString, SimpleString};
+use crate::storage::unix_time_millis;
+
+/// Entries returned by ACL LOG when no count is given.
+const DEFAULT_LOG_COUNT: usize = 10;
+
+fn describe_selector(selector: &Selector) -> Vec<Parser> {
+    vec![
+        BulkString("commands".to_string()),
+        BulkString(selector.describe_commands()),
+        BulkString("keys".to_string()),
+        BulkString(selector.describe_keys()),
+        BulkString("channels".to_string()),
+        BulkString(selector.describe_channels()),
+    ]
+}
+
+fn get_user(user: &User) -> Parser {
+    let mut fields = vec![
+        BulkString("flags".to_string()),
+        Array(user.flags()),
+        BulkString("passwords".to_string()),
+        Array(user.passwords.clone()),
+    ];
+    fields.extend(describe_selector(&user.root));
+    fields.push(BulkString("selectors".to_string()));
+    fields.push(NestedArray(user.selectors.iter().map(|selector| NestedArray(describe_selector(selector))).collect()));
+    NestedArray(fields)
+}
+
+/// Disconnects clients authenticated as users that no longer exist.
+fn disconnect_removed_users(info_server: &InfoServer) {
+    for client in info_server.clients.iter().filter(|client| info_server.acl.get(&client.user).is_none()) {
+        client.kill();
+    }
+}
+
+fn dry_run(args: &[String], info_server: &InfoServer) -> Result<Parser, AppError> {
+    let [username, command, command_args @ ..] = args else {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    };
+    let user = info_server.acl.get(username).ok_or_else(|| AppError::NoSuchUser(username.clone()))?;
+    let spec = find_command(&command.to_lowercase()).ok_or_else(|| AppError::UnknownCommand(command.clone()))?;
+    if !spec.accepts(command_args.len()) {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    }
+
+    match user.check(spec, command_args) {
+        Ok(()) => Ok(SimpleString("OK".to_string())),
+        Err(Denial::Command) => Ok(BulkString(format!("User {} has no permissions to run the '{}' command", username, spec.name))),
+        Err(Denial::Key(key)) => Ok(BulkString(format!("User {} has no permissions to access the '{}' key", username, key))),
+    }
+}
+
+pub fn acl(args: &[String], username: &str, info_server: &mut InfoServer) -> Result<Parser, AppError> {
+    let Some(subcommand) = args.first() else {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    };
+    let rest = &args[1..];
+
+    match subcommand.to_lowercase().as_str() {
+        "setuser" => {
+            let [name, rules @ ..] = rest else {
+                return Err(AppError::WrongNumberOfArgumentsError);
+            };
+            info_server.acl.set_user(name, rules)?;
+            Ok(SimpleString("OK".to_string()))
+        }
+        "getuser" => {
+            let [name] = rest else {
+                return Err(AppError::WrongNumberOfArgumentsError);
+            };
+            Ok(info_server.acl.get(name).map_or(NullBulkString, get_user))
+        }
+        "deluser" => {
+            if rest.is_empty() {
+                return Err(AppError::WrongNumberOfArgumentsError);
+            }
+            let deleted = info_server.acl.delete_users(rest)?;
+            disconnect_removed_users(info_server);
+            Ok(Integer(None, deleted.len() as u64))
+        }
+        "list" => Ok(Array(info_server.acl.users().map(User::describe).collect())),
+        "users" => Ok(Array(info_server.acl.users().map(|user| user.name.clone()).collect())),
+        "whoami" => Ok(BulkString(username.to_string())),
+        "cat" => match rest {
+            [] => Ok(Array(ACL_CATEGORIES.iter().map(|category| category.to_string()).collect())),
+            [category] => {
+                let category = category.to_lowercase();
+                if !ACL_CATEGORIES.contains(&category.as_str()) {
+                    return Err(AppError::UnknownAclCategory(category));
+                }
+                Ok(Array(
+                    COMMANDS
+                        .iter()
+                        .filter(|spec| spec.in_category(&category))
+                        .map(|spec| spec.name.to_string())
+                        .collect(),
+                ))
+            }
+            _ => Err(AppError::WrongNumberOfArgumentsError),
+        },
+        "log" => {
+            let count = match rest.first() {
+                None => DEFAULT_LOG_COUNT,
+                Some(option) if option.eq_ignore_ascii_case("reset") => {
+                    info_server.acl.log.reset();
+                    return Ok(SimpleString("OK".to_string()));
+                }
+                Some(count) => count.parse::<usize>().map_err(|_| AppError::InvalidInteger)?,
+            };
+
+            let entries = info_server
+                .acl
+                .log
+                .get(Some(count))
+                .map(|entry| {
+                    let age = unix_time_millis().saturating_sub(entry.created) as f64 / 1000.0;
+                    NestedArray(vec![
+                        BulkString("count".to_string()),
+                        Integer(None, entry.count),
+                        BulkString("reason".to_string()),
+                        BulkString(entry.reason.as_str().to_string()),
+                        BulkString("context".to_string()),
+                        BulkString("toplevel".to_string()),
+                        BulkString("object".to_string()),
+                        BulkString(entry.object.clone()),
+                        BulkString("username".to_string()),
+                        BulkString(entry.username.clone()),
+                        BulkString("age-seconds".to_string()),
+                        BulkString(format!("{:.3}", age)),
+                        BulkString("client-info".to_string()),
+                        BulkString(entry.client_info.clone()),
+                        BulkString("entry-id".to_string()),
+                        Integer(None, entry.id),
+                        BulkString("timestamp-created".to_string()),
+                        Integer(None, entry.created),
+                        BulkString("timestamp-last-updated".to_string()),
+                        Integer(None, entry.last_updated),
+                    ])
+                })
+                .collect();
+            Ok(NestedArray(entries))
+        }
+        "dryrun" => dry_run(rest, info_server),
+        "save" => {
+            info_server.acl.save()?;
+            Ok(SimpleString("OK".to_string()))
+        }
+        "load" => {
+            info_server.acl.load()?;
+            disconnect_removed_users(info_server);
+            Ok(SimpleString("OK".to_string()))
+        }
+        _ => Err(AppError::UnknownSubcommand(subcommand.clone())),
+    }
+}
@@ -0,0 +1,100 @@
+use crate::acl::log::AclLogReason;
+use crate::acl::user::DEFAULT_USER;
+use crate::commands::client::validate_client_name;
+use crate::config::info_server::InfoServer;
+use crate::constants::REDIS_VERSION;
+use crate::errors::app_errors::AppError;
+use crate::resp::parser::Parser;
+use crate::resp::parser::Parser::{BulkString, Integer, NestedArray, SimpleString};
+
+/// Authenticates the connection as `username`, or logs and counts the failure.
+fn login(
+    username: &str,
+    password: &str,
+    client_id: u64,
+    user: &mut Option<String>,
+    info_server: &mut InfoServer,
+) -> Result<(), AppError> {
+    if !info_server.acl.authenticate(username, password) {
+        let client_info = info_server.clients.get(client_id).map(|client| client.describe()).unwrap_or_default();
+        info_server.acl.log.record(AclLogReason::Auth, "AUTH", username, &client_info);
+        info_server.stats.acl_access_denied_auth += 1;
+        return Err(AppError::WrongPass);
+    }
+
+    *user = Some(username.to_string());
+    if let Some(client) = info_server.clients.get_mut(client_id) {
+        client.user = username.to_string();
+    }
+    Ok(())
+}
+
+/// `AUTH [username] password`. Without a username the default user is assumed.
+pub fn auth(args: &[String], client_id: u64, user: &mut Option<String>, info_server: &mut InfoServer) -> Result<Parser, AppError> {
+    match args {
+        [_] if info_server.acl.get(DEFAULT_USER).is_some_and(|default| default.nopass) => Err(AppError::AuthNotConfigured),
+        [password] => login(DEFAULT_USER, password, client_id, user, info_server),
+        [username, password] => login(username, password, client_id, user, info_server),
+        _ => Err(AppError::SyntaxError),
+    }?;
+    Ok(SimpleString("OK".to_string()))
+}
+
+/// `HELLO [protover [AUTH username password] [SETNAME clientname]]`. Only RESP2 is spoken, so
+/// the server properties are returned as a flat array of field/value pairs.
+pub fn hello(args: &[String], client_id: u64, user: &mut Option<String>, info_server: &mut InfoServer) -> Result<Parser, AppError> {
+    if let Some(protover) = args.first() {
+        match protover.parse::<u64>() {
+            Ok(2) => {}
+            Ok(_) => return Err(AppError::UnsupportedProtocol),
+            Err(_) => return Err(AppError::InvalidProtocolVersion),
+        }
+    }
+
+    let mut credentials = None;
+    let mut name = None;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].to_lowercase().as_str() {
+            "auth" if i + 2 < args.len() => {
+                credentials = Some((&args[i + 1], &args[i + 2]));
+                i += 3;
+            }
+            "setname" if i + 1 < args.len() => {
+                name = Some(&args[i + 1]);
+                i += 2;
+            }
+            _ => return Err(AppError::SyntaxError),
+        }
+    }
+
+    if let Some((username, password)) = credentials {
+        login(username, password, client_id, user, info_server)?;
+    }
+    if user.is_none() && info_server.acl.auth_required() {
+        return Err(AppError::HelloNoAuth);
+    }
+    if let Some(name) = name {
+        validate_client_name(name)?;
+        if let Some(client) = info_server.clients.get_mut(client_id) {
+            client.name = name.clone();
+        }
+    }
+
+    Ok(NestedArray(vec![
+        BulkString("server".to_string()),
+        BulkString("redis".to_string()),
+        BulkString("version".to_string()),
+        BulkString(REDIS_VERSION.to_string()),
+        BulkString("proto".to_string()),
+        Integer(None, 2),
+        BulkString("id".to_string()),
+        Integer(None, client_id),
+        BulkString("mode".to_string()),
+        BulkString("standalone".to_string()),
+        BulkString("role".to_string()),
+        BulkString(info_server.role.to_string()),
+        BulkString("modules".to_string()),
+        NestedArray(vec![]),
+    ]))
+}
@@ -0,0 +1,180 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::errors::app_errors::AppError;
+use crate::resp::parser::Parser;
+use crate::resp::parser::Parser::{BulkString, Integer, NullBulkString, SimpleString};
+use crate::types::client_registry::{ClientInfo, ClientRegistry, PauseMode};
+
+/// Client types accepted by CLIENT LIST TYPE and CLIENT KILL TYPE.
+const CLIENT_TYPES: &[&str] = &["normal", "master", "replica", "slave", "pubsub"];
+
+fn parse_client_type(value: &str) -> Result<String, AppError> {
+    let client_type = value.to_lowercase();
+    match CLIENT_TYPES.contains(&client_type.as_str()) {
+        true if client_type == "slave" => Ok("replica".to_string()),
+        true => Ok(client_type),
+        false => Err(AppError::UnknownClientType(value.to_string())),
+    }
+}
+
+/// Filters of the `CLIENT KILL <filter> <value> ...` form.
+#[derive(Default)]
+struct KillFilter {
+    id: Option<u64>,
+    addr: Option<String>,
+    laddr: Option<String>,
+    user: Option<String>,
+    client_type: Option<String>,
+    skip_me: bool,
+}
+
+impl KillFilter {
+    fn parse(args: &[String]) -> Result<Self, AppError> {
+        if !args.len().is_multiple_of(2) {
+            return Err(AppError::SyntaxError);
+        }
+
+        let mut filter = KillFilter { skip_me: true, ..KillFilter::default() };
+        for pair in args.chunks(2) {
+            let value = &pair[1];
+            match pair[0].to_lowercase().as_str() {
+                "id" => filter.id = Some(value.parse::<u64>().map_err(|_| AppError::InvalidInteger)?),
+                "addr" => filter.addr = Some(value.clone()),
+                "laddr" => filter.laddr = Some(value.clone()),
+                "user" => filter.user = Some(value.clone()),
+                "type" => filter.client_type = Some(parse_client_type(value)?),
+                "skipme" => filter.skip_me = match value.to_lowercase().as_str() {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(AppError::SyntaxError),
+                },
+                _ => return Err(AppError::SyntaxError),
+            }
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, client: &ClientInfo, caller: u64) -> bool {
+        !(self.skip_me && client.id == caller)
+            && self.id.is_none_or(|id| client.id == id)
+            && self.addr.as_ref().is_none_or(|addr| &client.addr == addr)
+            && self.laddr.as_ref().is_none_or(|laddr| &client.laddr == laddr)
+            && self.user.as_ref().is_none_or(|user| &client.user == user)
+            && self.client_type.as_ref().is_none_or(|client_type| client.client_type() == client_type)
+    }
+}
+
+/// Client names are shown in CLIENT LIST, so they must not break its space separated format.
+pub fn validate_client_name(name: &str) -> Result<(), AppError> {
+    match name.chars().all(|c| c.is_ascii_graphic()) {
+        true => Ok(()),
+        false => Err(AppError::InvalidClientName),
+    }
+}
+
+fn client_list(args: &[String], clients: &ClientRegistry) -> Result<String, AppError> {
+    let mut client_type = None;
+    let mut ids: Option<Vec<u64>> = None;
+
+    match args.first().map(|option| option.to_lowercase()).as_deref() {
+        None => {}
+        Some("type") if args.len() == 2 => client_type = Some(parse_client_type(&args[1])?),
+        Some("id") if args.len() > 1 => {
+            ids = Some(args[1..]
+                .iter()
+                .map(|id| id.parse::<u64>().map_err(|_| AppError::InvalidInteger))
+                .collect::<Result<Vec<u64>, AppError>>()?);
+        }
+        _ => return Err(AppError::SyntaxError),
+    }
+
+    Ok(clients
+        .iter()
+        .filter(|client| client_type.as_ref().is_none_or(|client_type| client.client_type() == client_type))
+        .filter(|client| ids.as_ref().is_none_or(|ids| ids.contains(&client.id)))
+        .map(|client| format!("{}\n", client.describe()))
+        .collect())
+}
+
+pub fn client(args: &[String], caller: u64, clients: &mut ClientRegistry) -> Result<Parser, AppError> {
+    let Some(subcommand) = args.first() else {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    };
+    let rest = &args[1..];
+
+    match subcommand.to_lowercase().as_str() {
+        "id" => Ok(Integer(None, caller)),
+        "getname" => match clients.get(caller) {
+            Some(client) if !client.name.is_empty() => Ok(BulkString(client.name.clone())),
+            _ => Ok(NullBulkString),
+        },
+        "setname" => {
+            let [name] = rest else {
+                return Err(AppError::WrongNumberOfArgumentsError);
+            };
+            validate_client_name(name)?;
+            if let Some(client) = clients.get_mut(caller) {
+                client.name = name.clone();
+            }
+            Ok(SimpleString("OK".to_string()))
+        }
+        "info" => {
+            let client = clients.get(caller).ok_or(AppError::NoSuchClient)?;
+            Ok(BulkString(format!("{}\n", client.describe())))
+        }
+        "list" => Ok(BulkString(client_list(rest, clients)?)),
+        "kill" => match rest {
+            [] => Err(AppError::WrongNumberOfArgumentsError),
+            // The old form takes a single address and fails when nobody is connected from it
+            [addr] => {
+                let client = clients.iter().find(|client| &client.addr == addr).ok_or(AppError::NoSuchClient)?;
+                client.kill();
+                Ok(SimpleString("OK".to_string()))
+            }
+            filters => {
+                let filter = KillFilter::parse(filters)?;
+                let mut killed = 0;
+                for client in clients.iter().filter(|client| filter.matches(client, caller)) {
+                    client.kill();
+                    killed += 1;
+                }
+                Ok(Integer(None, killed))
+            }
+        },
+        "pause" => {
+            let (timeout, mode) = match rest {
+                [timeout] => (timeout, PauseMode::All),
+                [timeout, mode] => (timeout, match mode.to_lowercase().as_str() {
+                    "write" => PauseMode::Write,
+                    "all" => PauseMode::All,
+                    _ => return Err(AppError::SyntaxError),
+                }),
+                _ => return Err(AppError::WrongNumberOfArgumentsError),
+            };
+            let timeout = timeout.parse::<u64>().map_err(|_| AppError::InvalidInteger)?;
+            // Like Redis, the deadline must fit in a signed millisecond timestamp
+            let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+            if timeout > i64::MAX as u64 - now_ms {
+                return Err(AppError::TimeoutOutOfRange);
+            }
+            let deadline = Instant::now().checked_add(Duration::from_millis(timeout)).ok_or(AppError::TimeoutOutOfRange)?;
+            clients.pause(deadline, mode);
+            Ok(SimpleString("OK".to_string()))
+        }
+        "unpause" => {
+            clients.unpause();
+            Ok(SimpleString("OK".to_string()))
+        }
+        "no-evict" => {
+            let enabled = match rest.first().map(|value| value.to_lowercase()).as_deref() {
+                Some("on") => true,
+                Some("off") => false,
+                _ => return Err(AppError::SyntaxError),
+            };
+            if let Some(client) = clients.get_mut(caller) {
+                client.no_evict = enabled;
+            }
+            Ok(SimpleString("OK".to_string()))
+        }
+        _ => Err(AppError::UnknownSubcommand(subcommand.clone())),
+    }
+}
@@ -0,0 +1,72 @@
+use crate::config::config_file::rewrite_config_file;
+use crate::config::info_server::InfoServer;
+use crate::config::registry::{find_parameter, PARAMETERS};
+use crate::errors::app_errors::AppError;
+use crate::logging;
+use crate::resp::parser::Parser;
+use crate::resp::parser::Parser::{Array, SimpleString};
+use crate::storage::{KeyspaceStats, Storage};
+use crate::utils::glob::glob_match;
+
+pub fn config(args: &[String], storage: &mut Storage, info_server: &mut InfoServer) -> Result<Parser, AppError> {
+    let Some(subcommand) = args.first() else {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    };
+
+    match subcommand.to_lowercase().as_str() {
+        "get" => {
+            if args.len() < 2 {
+                return Err(AppError::WrongNumberOfArgumentsError);
+            }
+
+            let patterns = &args[1..];
+            let mut reply = Vec::new();
+            for parameter in PARAMETERS {
+                // Aliases are only reported when asked for by their exact name
+                let name = if patterns.iter().any(|pattern| glob_match(pattern, parameter.name, true)) {
+                    parameter.name
+                } else if let Some(alias) = parameter.alias.filter(|alias| patterns.iter().any(|p| p.eq_ignore_ascii_case(alias))) {
+                    alias
+                } else {
+                    continue;
+                };
+                reply.push(name.to_string());
+                reply.push(parameter.get(&storage.config));
+            }
+            Ok(Array(reply))
+        }
+        "set" => {
+            if args.len() < 3 || args.len().is_multiple_of(2) {
+                return Err(AppError::WrongNumberOfArgumentsError);
+            }
+
+            // Either every parameter is applied or none is
+            let mut updated = storage.config.clone();
+            for pair in args[1..].chunks(2) {
+                let parameter = find_parameter(&pair[0])
+                    .ok_or_else(|| AppError::UnsupportedConfigParameter(pair[0].clone()))?;
+                if !parameter.mutable {
+                    return Err(AppError::ImmutableConfig(pair[0].clone()));
+                }
+                parameter.set(&mut updated, &pair[1])
+                    .map_err(|reason| AppError::InvalidConfigValue(pair[0].clone(), reason))?;
+            }
+            storage.config = updated;
+            storage.apply_config();
+            info_server.apply_config(&storage.config);
+            logging::apply_config(&storage.config);
+            Ok(SimpleString("OK".to_string()))
+        }
+        "rewrite" => {
+            let path = storage.config.config_file.clone().ok_or(AppError::NoConfigFile)?;
+            rewrite_config_file(&path, &storage.config)?;
+            Ok(SimpleString("OK".to_string()))
+        }
+        "resetstat" => {
+            storage.stats = KeyspaceStats::default();
+            info_server.stats.reset();
+            Ok(SimpleString("OK".to_string()))
+        }
+        _ => Err(AppError::UnknownSubcommand(subcommand.clone())),
+    }
+}
@@ -0,0 +1,410 @@
+use crate::errors::app_errors::AppError;
+use crate::geo::geohash;
+use crate::resp::parser::Parser;
+use crate::resp::parser::Parser::{BulkString, Integer, NestedArray, NullArray, NullBulkString};
+use crate::storage::Storage;
+use crate::types::sorted_set::SortedSet;
+
+enum Origin {
+    Member(String),
+    LonLat(f64, f64),
+}
+
+enum Shape {
+    Radius(f64),
+    Box(f64, f64),
+}
+
+#[derive(PartialEq)]
+enum Sort {
+    None,
+    Asc,
+    Desc,
+}
+
+struct GeoSearch {
+    origin: Origin,
+    shape: Shape,
+    unit: f64,
+    sort: Sort,
+    count: Option<usize>,
+    any: bool,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+    store_dist: bool,
+}
+
+struct GeoMatch {
+    member: String,
+    score: f64,
+    distance: f64,
+    longitude: f64,
+    latitude: f64,
+}
+
+fn parse_float(s: &str) -> Result<f64, AppError> {
+    s.parse::<f64>().ok().filter(|f| !f.is_nan()).ok_or(AppError::InvalidFloat)
+}
+
+fn parse_coordinates(lon: &str, lat: &str) -> Result<(f64, f64), AppError> {
+    let (longitude, latitude) = (parse_float(lon)?, parse_float(lat)?);
+    if !geohash::is_valid_coordinate(longitude, latitude) {
+        return Err(AppError::InvalidCoordinates(longitude, latitude));
+    }
+    Ok((longitude, latitude))
+}
+
+/// Returns the number of meters in one `unit`.
+fn parse_unit(unit: &str) -> Result<f64, AppError> {
+    match unit.to_lowercase().as_str() {
+        "m" => Ok(1.0),
+        "km" => Ok(1000.0),
+        "ft" => Ok(0.3048),
+        "mi" => Ok(1609.34),
+        _ => Err(AppError::UnsupportedUnit),
+    }
+}
+
+fn format_float(f: f64) -> String {
+    format!("{}", f)
+}
+
+fn coordinates_reply(longitude: f64, latitude: f64) -> Parser {
+    NestedArray(vec![BulkString(format_float(longitude)), BulkString(format_float(latitude))])
+}
+
+pub fn geoadd(args: &[String], storage: &mut Storage) -> Result<Parser, AppError> {
+    if args.len() < 4 {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    }
+
+    let (mut nx, mut xx, mut ch) = (false, false, false);
+    let mut index = 1;
+    while index < args.len() {
+        match args[index].to_lowercase().as_str() {
+            "nx" => nx = true,
+            "xx" => xx = true,
+            "ch" => ch = true,
+            _ => break,
+        }
+        index += 1;
+    }
+
+    if nx && xx {
+        return Err(AppError::IncompatibleOptions("XX and NX options at the same time are not compatible"));
+    }
+
+    let triples = &args[index..];
+    if triples.is_empty() || !triples.len().is_multiple_of(3) {
+        return Err(AppError::SyntaxError);
+    }
+
+    let mut members = Vec::with_capacity(triples.len() / 3);
+    for triple in triples.chunks(3) {
+        let (longitude, latitude) = parse_coordinates(&triple[0], &triple[1])?;
+        members.push((triple[2].clone(), geohash::encode(longitude, latitude) as f64));
+    }
+
+    let set = storage.get_sorted_set_mut(&args[0])?;
+    let (mut count, mut modified) = (0, 0);
+    for (member, score) in members {
+        let previous = set.score(&member);
+        match previous {
+            Some(_) if nx => continue,
+            None if xx => continue,
+            Some(old) if old == score => continue,
+            _ => {}
+        }
+
+        set.insert(member, score);
+        modified += 1;
+        if previous.is_none() || ch {
+            count += 1;
+        }
+    }
+
+    // XX on a missing key leaves behind the empty set created above, which was never a change
+    if set.is_empty() {
+        storage.items.remove(&args[0]);
+    }
+    storage.add_changes(modified);
+
+    Ok(Integer(None, count))
+}
+
+pub fn geopos(args: &[String], storage: &mut Storage) -> Result<Parser, AppError> {
+    if args.is_empty() {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    }
+
+    let set = storage.get_sorted_set(&args[0])?;
+    let positions = args[1..]
+        .iter()
+        .map(|member| match set.and_then(|s| s.score(member)) {
+            Some(score) => {
+                let (longitude, latitude) = geohash::decode(score as u64);
+                coordinates_reply(longitude, latitude)
+            }
+            None => NullArray,
+        })
+        .collect();
+
+    Ok(NestedArray(positions))
+}
+
+pub fn geodist(args: &[String], storage: &mut Storage) -> Result<Parser, AppError> {
+    if args.len() != 3 && args.len() != 4 {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    }
+
+    let unit = match args.get(3) {
+        Some(unit) => parse_unit(unit)?,
+        None => 1.0,
+    };
+
+    let Some(set) = storage.get_sorted_set(&args[0])? else {
+        return Ok(NullBulkString);
+    };
+
+    match (set.score(&args[1]), set.score(&args[2])) {
+        (Some(a), Some(b)) => {
+            let (lon1, lat1) = geohash::decode(a as u64);
+            let (lon2, lat2) = geohash::decode(b as u64);
+            Ok(BulkString(format!("{:.4}", geohash::distance(lon1, lat1, lon2, lat2) / unit)))
+        }
+        _ => Ok(NullBulkString),
+    }
+}
+
+pub fn geohash(args: &[String], storage: &mut Storage) -> Result<Parser, AppError> {
+    if args.is_empty() {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    }
+
+    let set = storage.get_sorted_set(&args[0])?;
+    let hashes = args[1..]
+        .iter()
+        .map(|member| match set.and_then(|s| s.score(member)) {
+            Some(score) => {
+                let (longitude, latitude) = geohash::decode(score as u64);
+                BulkString(geohash::to_base32(longitude, latitude))
+            }
+            None => NullBulkString,
+        })
+        .collect();
+
+    Ok(NestedArray(hashes))
+}
+
+fn parse_search_args(args: &[String], allow_store_dist: bool) -> Result<GeoSearch, AppError> {
+    let mut origin = None;
+    let mut shape = None;
+    let mut unit = 1.0;
+    let mut search = GeoSearch {
+        origin: Origin::LonLat(0.0, 0.0),
+        shape: Shape::Radius(0.0),
+        unit: 1.0,
+        sort: Sort::None,
+        count: None,
+        any: false,
+        with_coord: false,
+        with_dist: false,
+        with_hash: false,
+        store_dist: false,
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let remaining = args.len() - i - 1;
+        match args[i].to_lowercase().as_str() {
+            "frommember" if remaining >= 1 => {
+                if origin.is_some() {
+                    return Err(AppError::IncompatibleOptions("exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH"));
+                }
+                origin = Some(Origin::Member(args[i + 1].clone()));
+                i += 1;
+            }
+            "fromlonlat" if remaining >= 2 => {
+                if origin.is_some() {
+                    return Err(AppError::IncompatibleOptions("exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH"));
+                }
+                let (longitude, latitude) = parse_coordinates(&args[i + 1], &args[i + 2])?;
+                origin = Some(Origin::LonLat(longitude, latitude));
+                i += 2;
+            }
+            "byradius" if remaining >= 2 => {
+                if shape.is_some() {
+                    return Err(AppError::IncompatibleOptions("exactly one of BYRADIUS and BYBOX arguments must be provided for GEOSEARCH command"));
+                }
+                let radius = parse_float(&args[i + 1])?;
+                if radius < 0.0 {
+                    return Err(AppError::IncompatibleOptions("radius cannot be negative"));
+                }
+                unit = parse_unit(&args[i + 2])?;
+                shape = Some(Shape::Radius(radius));
+                i += 2;
+            }
+            "bybox" if remaining >= 3 => {
+                if shape.is_some() {
+                    return Err(AppError::IncompatibleOptions("exactly one of BYRADIUS and BYBOX arguments must be provided for GEOSEARCH command"));
+                }
+                let (width, height) = (parse_float(&args[i + 1])?, parse_float(&args[i + 2])?);
+                if width < 0.0 || height < 0.0 {
+                    return Err(AppError::IncompatibleOptions("height or width cannot be negative"));
+                }
+                unit = parse_unit(&args[i + 3])?;
+                shape = Some(Shape::Box(width, height));
+                i += 3;
+            }
+            "asc" => search.sort = Sort::Asc,
+            "desc" => search.sort = Sort::Desc,
+            "count" if remaining >= 1 => {
+                let count = args[i + 1].parse::<i64>().map_err(|_| AppError::InvalidInteger)?;
+                if count <= 0 {
+                    return Err(AppError::IncompatibleOptions("COUNT must be > 0"));
+                }
+                search.count = Some(count as usize);
+                i += 1;
+                if args.get(i + 1).is_some_and(|a| a.eq_ignore_ascii_case("any")) {
+                    search.any = true;
+                    i += 1;
+                }
+            }
+            "withcoord" if !allow_store_dist => search.with_coord = true,
+            "withdist" if !allow_store_dist => search.with_dist = true,
+            "withhash" if !allow_store_dist => search.with_hash = true,
+            "storedist" if allow_store_dist => search.store_dist = true,
+            _ => return Err(AppError::SyntaxError),
+        }
+        i += 1;
+    }
+
+    search.origin = origin
+        .ok_or(AppError::IncompatibleOptions("exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH"))?;
+    search.shape = shape
+        .ok_or(AppError::IncompatibleOptions("exactly one of BYRADIUS and BYBOX arguments must be provided for GEOSEARCH command"))?;
+    search.unit = unit;
+
+    if search.any && search.count.is_none() {
+        return Err(AppError::IncompatibleOptions("the ANY argument requires COUNT argument"));
+    }
+    // Without ANY, a COUNT limit is only meaningful for the closest members
+    if search.count.is_some() && !search.any && search.sort == Sort::None {
+        search.sort = Sort::Asc;
+    }
+
+    Ok(search)
+}
+
+fn search_members(set: &SortedSet, search: &GeoSearch) -> Result<Vec<GeoMatch>, AppError> {
+    let (center_lon, center_lat) = match &search.origin {
+        Origin::LonLat(longitude, latitude) => (*longitude, *latitude),
+        Origin::Member(member) => {
+            let score = set.score(member).ok_or(AppError::MemberNotFound)?;
+            geohash::decode(score as u64)
+        }
+    };
+
+    let mut matches = Vec::new();
+    for (member, score) in set.iter() {
+        let (longitude, latitude) = geohash::decode(score as u64);
+        let distance = match search.shape {
+            Shape::Radius(radius) => {
+                let distance = geohash::distance(center_lon, center_lat, longitude, latitude);
+                if distance > radius * search.unit {
+                    continue;
+                }
+                distance
+            }
+            Shape::Box(width, height) => {
+                let half_height = height * search.unit / 2.0;
+                let half_width = width * search.unit / 2.0;
+                if geohash::latitude_distance(center_lat, latitude) > half_height
+                    || geohash::distance(center_lon, latitude, longitude, latitude) > half_width {
+                    continue;
+                }
+                geohash::distance(center_lon, center_lat, longitude, latitude)
+            }
+        };
+
+        matches.push(GeoMatch { member: member.to_string(), score, distance, longitude, latitude });
+
+        if search.any && Some(matches.len()) == search.count {
+            break;
+        }
+    }
+
+    match search.sort {
+        Sort::Asc => matches.sort_by(|a, b| a.distance.total_cmp(&b.distance)),
+        Sort::Desc => matches.sort_by(|a, b| b.distance.total_cmp(&a.distance)),
+        Sort::None => {}
+    }
+    if let Some(count) = search.count {
+        matches.truncate(count);
+    }
+
+    Ok(matches)
+}
+
+pub fn geosearch(args: &[String], storage: &mut Storage) -> Result<Parser, AppError> {
+    if args.len() < 5 {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    }
+
+    let search = parse_search_args(&args[1..], false)?;
+    let Some(set) = storage.get_sorted_set(&args[0])? else {
+        return Ok(NestedArray(vec![]));
+    };
+
+    let plain = !search.with_coord && !search.with_dist && !search.with_hash;
+    let replies = search_members(set, &search)?
+        .into_iter()
+        .map(|m| {
+            if plain {
+                return BulkString(m.member);
+            }
+
+            let mut entry = vec![BulkString(m.member)];
+            if search.with_dist {
+                entry.push(BulkString(format!("{:.4}", m.distance / search.unit)));
+            }
+            if search.with_hash {
+                entry.push(Integer(None, m.score as u64));
+            }
+            if search.with_coord {
+                entry.push(coordinates_reply(m.longitude, m.latitude));
+            }
+            NestedArray(entry)
+        })
+        .collect();
+
+    Ok(NestedArray(replies))
+}
+
+pub fn geosearchstore(args: &[String], storage: &mut Storage) -> Result<Parser, AppError> {
+    if args.len() < 6 {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    }
+
+    let search = parse_search_args(&args[2..], true)?;
+    let matches = match storage.get_sorted_set(&args[1])? {
+        Some(set) => search_members(set, &search)?,
+        None => vec![],
+    };
+
+    let count = matches.len() as u64;
+    if matches.is_empty() {
+        storage.del(vec![&args[0]]);
+        return Ok(Integer(None, 0));
+    }
+
+    let mut destination = SortedSet::new();
+    for m in matches {
+        let score = if search.store_dist { m.distance / search.unit } else { m.score };
+        destination.insert(m.member, score);
+    }
+    storage.set_sorted_set(args[0].clone(), destination);
+
+    Ok(Integer(None, count))
+}
@@ -1,12 +1,25 @@
 use std::io::{Error, ErrorKind};
 use std::sync::Arc;
-use tokio::net::TcpStream;
-use tokio::sync::Mutex;
-use crate::storage::Storage;
-use std::{format, println};
-use crate::config::info_server::InfoServer;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex, MutexGuard};
+use crate::storage::{unix_time_millis, Item, Storage};
+use std::format;
+use crate::commands::{acl, auth, client, config, geo, info, monitor, slowlog};
+use crate::acl::log::AclLogReason;
+use crate::acl::selector::Denial;
+use crate::acl::user::DEFAULT_USER;
+use crate::commands::table::{find_command, CommandSpec, ADMIN, NO_AUTH, SENSITIVE, WRITE};
+use crate::config::buffer_limits::ClientClass;
+use crate::config::info_server::{CommandOutcome, InfoServer};
+use crate::enums::value::Value;
 use crate::errors::app_errors::AppError;
-use crate::resp::handler::RespHandler;
+use crate::logging;
+use crate::logging::LogLevel;
+use crate::persistence::bgsave::{bgsave, BgsaveStatus};
+use crate::persistence::rewrite::{bgrewriteaof, RewriteStatus};
+use crate::resp::handler::{ClientStream, RespHandler};
+use crate::servers::shutdown::{parse_shutdown_args, shutdown, ShutdownRequest};
+use crate::types::client_registry::{until_killed, ClientHandle, ClientPause};
 use crate::resp::parser::extract_set_command_args;
 use crate::resp::parser::Parser::{Array, BulkString, Integer, NullBulkString, SimpleError, SimpleString};
 
@@ -19,99 +32,473 @@ macro_rules! verify_args {
     }};
 }
 
-pub async fn handle_connection(stream: TcpStream, storage: Arc<Mutex<Storage>>, info_server: Arc<Mutex<InfoServer>>) -> Result<(), Error> {
+/// Per-connection state threaded through command dispatch.
+struct Session {
+    client: ClientHandle,
+    addr: String,
+    /// The user set by AUTH or HELLO AUTH. Until then the connection acts as the default user,
+    /// as long as that one needs no password.
+    user: Option<String>,
+    /// Set by QUIT, closing the connection once the reply is sent.
+    quit: bool,
+    /// Time the current command spent waiting for shared locks, which is not counted as its execution time.
+    lock_wait: Duration,
+}
+
+/// Serves one client until it disconnects. `addr` and `laddr` are the remote and local
+/// addresses shown by CLIENT LIST.
+pub async fn handle_connection<S: ClientStream>(
+    stream: S,
+    addr: String,
+    laddr: String,
+    storage: Arc<Mutex<Storage>>,
+    info_server: Arc<Mutex<InfoServer>>,
+) -> Result<(), Error> {
     let mut handler = RespHandler::new(stream);
+    let client = {
+        let mut info_server = info_server.lock().await;
+        // Checked under the same lock as the registration, so concurrent connections cannot overshoot
+        if info_server.clients.is_full() {
+            info_server.stats.rejected_connections += 1;
+            drop(info_server);
+            // The client is dropped whether or not this reaches it
+            if handler.response(SimpleError(AppError::MaxClients.to_string())).await.is_ok() {
+                let _ = handler.flush().await;
+            }
+            return Ok(());
+        }
+        handler.set_limits(info_server.clients.buffer_limits(ClientClass::Normal));
+        info_server.clients.register(addr.clone(), laddr)
+    };
+    let client_id = client.id;
+    let mut session = Session { client, addr, user: None, quit: false, lock_wait: Duration::ZERO };
+
+    let result = serve(&mut handler, &mut session, &storage, &info_server).await;
+    let result = match result {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+            log_verbose!("Connection closed by client {}", session.addr);
+            // A client that only shut down its writing side still reads the replies queued so far
+            tokio::select! {
+                _ = handler.flush() => {}
+                _ = until_killed(&mut session.client.killed) => {}
+            }
+            Ok(())
+        }
+        Err(e) if e.kind() == ErrorKind::InvalidData => {
+            log_warning!("Closing client {}: {}", session.addr, e);
+            // Still try to deliver the reply to a protocol error, unless the client gets killed first
+            tokio::select! {
+                _ = handler.flush() => {}
+                _ = until_killed(&mut session.client.killed) => {}
+            }
+            Ok(())
+        }
+        Err(e) => Err(e),
+        Ok(()) => {
+            tokio::select! {
+                _ = handler.flush() => {}
+                _ = until_killed(&mut session.client.killed) => {}
+            }
+            Ok(())
+        }
+    };
+    info_server.lock().await.clients.unregister(client_id);
+    result
+}
 
+/// Why a write command may not run, such as the AOF failing to accept writes.
+async fn write_refusal(spec: &CommandSpec, storage: &Arc<Mutex<Storage>>) -> Option<AppError> {
+    match spec.has_flag(WRITE) {
+        true => storage.lock().await.write_refusal(),
+        false => None,
+    }
+}
+
+/// Marks whether a client is held back by a CLIENT PAUSE, for INFO and CLIENT LIST.
+async fn set_blocked(info_server: &Arc<Mutex<InfoServer>>, client_id: u64, blocked: bool) {
+    if let Some(client) = info_server.lock().await.clients.get_mut(client_id) {
+        client.blocked = blocked;
+    }
+}
+
+/// Waits until no CLIENT PAUSE holds back a command of this kind.
+async fn wait_while_paused(pause: &mut watch::Receiver<Option<ClientPause>>, is_write: bool) {
     loop {
-        match handler.get_command_with_args().await {
-            Ok((command, args)) => {
-                println!("Command '{}' received with args: {:?}", command, args);
-                match command.as_str() {
-                    "ping" => handler.response(SimpleString("PONG".to_string())).await?,
-                    "echo" => {
-                        if !args.is_empty() {
-                            handler.response(SimpleString(args[0].to_string())).await?
-                        } else {
-                            handler.response(SimpleError(AppError::WrongNumberOfArgumentsError.to_string())).await?;
-                        }
-                    }
-                    "set" => {
-                        let set_command_args = extract_set_command_args(args).await;
-                        match set_command_args {
-                            Ok((key, value, exp)) => {
-                                let mut storage = storage.lock().await;
-                                storage.set(key, value, exp);
-                                handler.response(SimpleString("OK".to_string())).await?;
-                            }
-                            Err(e) => {
-                                handler.response(SimpleError(e.to_string())).await?
-                            }
-                        }
-                    }
-                    "get" => {
-                        verify_args!(args.len() < 1, handler);
-
-                        let response = {
-                            let mut storage = storage.lock().await;
-                            match storage.get(args[0].as_str()) {
-                                Some(item) => SimpleString(item.value.clone()),
-                                None => NullBulkString,
-                            }
-                        };
-
-                        handler.response(response).await?;
-                    }
-                    "del" => {
-                        verify_args!(args.len() < 1, handler);
+        let current = *pause.borrow_and_update();
+        let Some(current) = current.filter(|pause| pause.holds(is_write)) else {
+            return;
+        };
+        tokio::select! {
+            _ = tokio::time::sleep_until(current.until.into()) => {}
+            _ = pause.changed() => {}
+        }
+    }
+}
 
-                        let count_deleted_keys = {
-                            let mut storage = storage.lock().await;
-                            storage.del(args.iter().map(|s| s.as_str()).collect())
-                        };
+/// The arguments as they may be logged: those of commands carrying secrets are hidden.
+fn redacted_args(spec: Option<&CommandSpec>, args: &[String]) -> Vec<String> {
+    match spec.is_some_and(|spec| spec.has_flag(SENSITIVE)) {
+        true => vec!["(redacted)".to_string(); args.len()],
+        false => args.to_vec(),
+    }
+}
 
-                        handler.response(Integer(None, count_deleted_keys)).await?
-                    }
-                    "info" => {
-                        verify_args!(args.len() < 1, handler);
-                        let mut info_server = info_server.lock().await;
-                        let info_string = info_server.get_info_string();
-                        handler.response(BulkString(info_string)).await?;
+/// Checks that the connection is authenticated and its user may run the command, logging and
+/// counting refusals. Commands usable before authentication are never refused.
+async fn authorize(session: &Session, spec: &CommandSpec, args: &[String], info_server: &Arc<Mutex<InfoServer>>) -> Result<(), AppError> {
+    if spec.has_flag(NO_AUTH) {
+        return Ok(());
+    }
+    let mut guard = info_server.lock().await;
+    let info_server = &mut *guard;
+    if session.user.is_none() && info_server.acl.auth_required() {
+        return Err(AppError::NoAuth);
+    }
+
+    let username = session.user.as_deref().unwrap_or(DEFAULT_USER);
+    let Err(denial) = info_server.acl.check(username, spec, args) else {
+        return Ok(());
+    };
+    let client_info = info_server.clients.get(session.client.id).map(|client| client.describe()).unwrap_or_default();
+    let (reason, object, error) = match denial {
+        Denial::Command => {
+            info_server.stats.acl_access_denied_cmd += 1;
+            (AclLogReason::Command, spec.name.to_string(), AppError::NoPermCommand(username.to_string(), spec.name.to_string()))
+        }
+        Denial::Key(key) => {
+            info_server.stats.acl_access_denied_key += 1;
+            (AclLogReason::Key, key, AppError::NoPermKey)
+        }
+    };
+    info_server.acl.log.record(reason, &object, username, &client_info);
+    Err(error)
+}
+
+async fn serve<S: ClientStream>(
+    handler: &mut RespHandler<S>,
+    session: &mut Session,
+    storage: &Arc<Mutex<Storage>>,
+    info_server: &Arc<Mutex<InfoServer>>,
+) -> Result<(), Error> {
+    loop {
+        let (command, args) = tokio::select! {
+            received = handler.get_command_with_args() => received?,
+            _ = until_killed(&mut session.client.killed) => return Ok(()),
+        };
+        let spec = find_command(&command);
+        if logging::enabled(LogLevel::Debug) {
+            log_debug!("Command '{}' received with args: {:?}", command, redacted_args(spec, &args));
+        }
+
+        let rejection = match spec {
+            None => Some((AppError::UnknownCommand(command.clone()), CommandOutcome::Unknown)),
+            Some(spec) if !spec.accepts(args.len()) => Some((AppError::WrongNumberOfArgumentsError, CommandOutcome::Rejected)),
+            Some(spec) => match authorize(session, spec, &args, info_server).await {
+                Err(error) => Some((error, CommandOutcome::Rejected)),
+                Ok(()) => {
+                    let is_write = spec.has_flag(WRITE);
+                    let paused = session.client.pause.borrow().is_some_and(|pause| pause.holds(is_write));
+                    if paused {
+                        set_blocked(info_server, session.client.id, true).await;
                     }
-                    "replconf" => {
-                        handler.response(SimpleString("OK".to_string())).await?
+                    tokio::select! {
+                        _ = wait_while_paused(&mut session.client.pause, is_write) => {}
+                        _ = until_killed(&mut session.client.killed) => return Ok(()),
                     }
-                    "psync" => {
-                        let info_server = info_server.lock().await;
-                        handler
-                            .response(SimpleString(format!("FULLRESYNC {} {}", info_server.master_replid, info_server.master_repl_offset)))
-                            .await?;
+                    if paused {
+                        set_blocked(info_server, session.client.id, false).await;
                     }
-                    "keys" => {
-                        verify_args!(args.len() != 1, handler);
-                        let mut storage = storage.lock().await;
-                        match storage.keys(&args[0]) {
-                            Ok(keys) => handler.response(Array(keys)).await?,
-                            Err(e) => handler.response(SimpleError(e.to_string())).await?
+                    write_refusal(spec, storage).await.map(|error| (error, CommandOutcome::Rejected))
+                }
+            },
+        };
+
+        session.lock_wait = Duration::ZERO;
+        let started = Instant::now();
+        let outcome = match rejection {
+            Some((error, outcome)) => {
+                handler.response(SimpleError(error.to_string())).await?;
+                outcome
+            }
+            None => {
+                dispatch(handler, session, command.clone(), &args, storage, info_server).await?;
+                CommandOutcome::Executed
+            }
+        };
+        let duration = started.elapsed().saturating_sub(session.lock_wait);
+        let counters = handler.take_counters();
+        let (query_buffer, query_buffer_capacity) = handler.query_buffer();
+        let output_buffer = handler.output_buffer();
+
+        let mut info_server = info_server.lock().await;
+        info_server.stats.record_command(&command, outcome, duration, counters);
+        // Limits follow CONFIG SET and the client becoming a replica
+        let class = info_server.clients.get(session.client.id).map_or(ClientClass::Normal, |client| client.class());
+        handler.set_limits(info_server.clients.buffer_limits(class));
+        let client_name = match info_server.clients.get_mut(session.client.id) {
+            Some(client) => {
+                client.last_interaction = Instant::now();
+                client.last_command = command.clone();
+                client.query_buffer = query_buffer;
+                client.query_buffer_capacity = query_buffer_capacity;
+                client.output_buffer = output_buffer;
+                client.name.clone()
+            }
+            None => String::new(),
+        };
+        if outcome == CommandOutcome::Executed {
+            let args = redacted_args(spec, &args);
+            info_server.slow_log.record(&command, &args, duration.as_micros() as u64, &session.addr, &client_name);
+            if spec.is_some_and(|spec| !spec.has_flag(ADMIN)) {
+                info_server.feed_monitors(&command, &args, &session.addr);
+            }
+        }
+        if session.quit {
+            return Ok(());
+        }
+    }
+}
+
+/// Locks `mutex` for a command, adding the time spent waiting for it to `waited`.
+async fn lock<'a, T>(mutex: &'a Mutex<T>, waited: &mut Duration) -> MutexGuard<'a, T> {
+    let started = Instant::now();
+    let guard = mutex.lock().await;
+    *waited += started.elapsed();
+    guard
+}
+
+async fn dispatch<S: ClientStream>(
+    handler: &mut RespHandler<S>,
+    session: &mut Session,
+    command: String,
+    args: &[String],
+    storage: &Arc<Mutex<Storage>>,
+    info_server: &Arc<Mutex<InfoServer>>,
+) -> Result<(), Error> {
+    match command.as_str() {
+        "ping" => handler.response(SimpleString("PONG".to_string())).await?,
+        "echo" => {
+            if !args.is_empty() {
+                handler.response(SimpleString(args[0].to_string())).await?
+            } else {
+                handler.response(SimpleError(AppError::WrongNumberOfArgumentsError.to_string())).await?;
+            }
+        }
+        "set" => {
+            let set_command_args = extract_set_command_args(args.to_vec()).await;
+            match set_command_args {
+                Ok((key, value, exp)) => {
+                    // Relative expirations are logged as absolute ones so replays keep the original deadline
+                    let propagated = match exp {
+                        0 => vec![key.clone(), value.clone()],
+                        _ => {
+                            let Some(deadline) = unix_time_millis().checked_add(exp as u64) else {
+                                handler.response(SimpleError(AppError::InvalidExpirationValue.to_string())).await?;
+                                return Ok(());
+                            };
+                            vec![key.clone(), value.clone(), "PXAT".to_string(), deadline.to_string()]
                         }
-                    }
-                    "save" => {
-                        let mut storage = storage.lock().await;
-                        storage.save_rdb_file().unwrap();
-                        handler.response(SimpleString("OK".to_string())).await?
-                    }
-                    c => {
-                        handler.response(SimpleError(format!("Unknown command: {}", c))).await?;
-                    }
+                    };
+                    let mut storage = lock(storage, &mut session.lock_wait).await;
+                    storage.set(key, value, exp);
+                    let response = match storage.propagate("set", &propagated) {
+                        Ok(()) => SimpleString("OK".to_string()),
+                        Err(e) => SimpleError(e.to_string()),
+                    };
+                    drop(storage);
+                    handler.response(response).await?;
+                }
+                Err(e) => {
+                    handler.response(SimpleError(e.to_string())).await?
                 }
             }
-            Err(e) => {
-                return if e.kind() == ErrorKind::UnexpectedEof {
-                    println!("Connection closed by client");
-                    Ok(())
-                } else {
-                    Err(e)
+        }
+        "get" => {
+            verify_args!(args.is_empty(), handler);
+
+            let response = {
+                let mut storage = lock(storage, &mut session.lock_wait).await;
+                match storage.get(args[0].as_str()) {
+                    Some(Item { value: Value::String(value), .. }) => SimpleString(value.clone()),
+                    Some(_) => SimpleError(AppError::WrongType.to_string()),
+                    None => NullBulkString,
                 }
+            };
+
+            handler.response(response).await?;
+        }
+        "del" => {
+            verify_args!(args.is_empty(), handler);
+
+            let response = {
+                let mut storage = lock(storage, &mut session.lock_wait).await;
+                let deleted = storage.del(args.iter().map(|s| s.as_str()).collect());
+                match deleted {
+                    0 => Ok(deleted),
+                    _ => storage.propagate("del", args).map(|_| deleted),
+                }
+            };
+
+            match response {
+                Ok(deleted) => handler.response(Integer(None, deleted.into())).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?,
             }
         }
+        "info" => {
+            let info_string = {
+                let storage = lock(storage, &mut session.lock_wait).await;
+                let info_server = lock(info_server, &mut session.lock_wait).await;
+                info::info(args, &storage, &info_server)
+            };
+            handler.response(BulkString(info_string)).await?;
+        }
+        "auth" | "hello" => {
+            let result = {
+                let mut info_server = lock(info_server, &mut session.lock_wait).await;
+                match command.as_str() {
+                    "auth" => auth::auth(args, session.client.id, &mut session.user, &mut info_server),
+                    _ => auth::hello(args, session.client.id, &mut session.user, &mut info_server),
+                }
+            };
+
+            match result {
+                Ok(response) => handler.response(response).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "quit" => {
+            session.quit = true;
+            handler.response(SimpleString("OK".to_string())).await?
+        }
+        "replconf" => {
+            handler.response(SimpleString("OK".to_string())).await?
+        }
+        "psync" => {
+            let mut info_server = lock(info_server, &mut session.lock_wait).await;
+            if let Some(client) = info_server.clients.get_mut(session.client.id) {
+                client.replica = true;
+            }
+            handler
+                .response(SimpleString(format!("FULLRESYNC {} {}", info_server.master_replid, info_server.master_repl_offset)))
+                .await?;
+        }
+        "keys" => {
+            verify_args!(args.len() != 1, handler);
+            let mut storage = lock(storage, &mut session.lock_wait).await;
+            match storage.keys(&args[0]) {
+                Ok(keys) => handler.response(Array(keys)).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "save" => {
+            let result = lock(storage, &mut session.lock_wait).await.save_rdb_file();
+            match result {
+                Ok(()) => handler.response(SimpleString("OK".to_string())).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "monitor" => {
+            let feed = {
+                let mut info_server = lock(info_server, &mut session.lock_wait).await;
+                if let Some(client) = info_server.clients.get_mut(session.client.id) {
+                    client.monitor = true;
+                }
+                info_server.monitors.subscribe()
+            };
+            handler.response(SimpleString("OK".to_string())).await?;
+            monitor::monitor(handler, feed, &mut session.client.killed).await?;
+            // A monitoring client never goes back to running commands
+            session.quit = true;
+        }
+        "client" => {
+            let result = client::client(args, session.client.id, &mut lock(info_server, &mut session.lock_wait).await.clients);
+            match result {
+                Ok(response) => handler.response(response).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "acl" => {
+            let username = session.user.as_deref().unwrap_or(DEFAULT_USER);
+            let result = acl::acl(args, username, &mut *lock(info_server, &mut session.lock_wait).await);
+            match result {
+                Ok(response) => handler.response(response).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "slowlog" => {
+            let result = slowlog::slowlog(args, &mut lock(info_server, &mut session.lock_wait).await.slow_log);
+            match result {
+                Ok(response) => handler.response(response).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "config" => {
+            let result = {
+                let mut storage = lock(storage, &mut session.lock_wait).await;
+                let mut info_server = lock(info_server, &mut session.lock_wait).await;
+                config::config(args, &mut storage, &mut info_server)
+            };
+
+            match result {
+                Ok(response) => handler.response(response).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "geoadd" | "geopos" | "geodist" | "geohash" | "geosearch" | "geosearchstore" => {
+            let result = {
+                let mut storage = lock(storage, &mut session.lock_wait).await;
+                let result = match command.as_str() {
+                    "geoadd" => geo::geoadd(args, &mut storage),
+                    "geopos" => geo::geopos(args, &mut storage),
+                    "geodist" => geo::geodist(args, &mut storage),
+                    "geohash" => geo::geohash(args, &mut storage),
+                    "geosearch" => geo::geosearch(args, &mut storage),
+                    _ => geo::geosearchstore(args, &mut storage),
+                };
+                match result {
+                    Ok(response) if matches!(command.as_str(), "geoadd" | "geosearchstore") => {
+                        storage.propagate(&command, args).map(|_| response)
+                    }
+                    result => result,
+                }
+            };
+
+            match result {
+                Ok(response) => handler.response(response).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "bgsave" => {
+            let schedule = args.first().is_some_and(|a| a.eq_ignore_ascii_case("schedule"));
+            match bgsave(storage, schedule).await {
+                Ok(BgsaveStatus::Started) => handler.response(SimpleString("Background saving started".to_string())).await?,
+                Ok(BgsaveStatus::Scheduled) => handler.response(SimpleString("Background saving scheduled".to_string())).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "bgrewriteaof" => {
+            match bgrewriteaof(storage).await {
+                Ok(RewriteStatus::Started) => handler.response(SimpleString("Background append only file rewriting started".to_string())).await?,
+                Ok(RewriteStatus::Scheduled) => handler.response(SimpleString("Background append only file rewriting scheduled".to_string())).await?,
+                Err(e) => handler.response(SimpleError(e.to_string())).await?
+            }
+        }
+        "shutdown" => {
+            let error = match parse_shutdown_args(args) {
+                Ok(ShutdownRequest::Shutdown(options)) => shutdown(options, storage, info_server).await,
+                // Shutdowns never wait for replicas, so there is no window in which one can be aborted
+                Ok(ShutdownRequest::Abort) => AppError::NoShutdownInProgress,
+                Err(e) => e,
+            };
+            handler.response(SimpleError(error.to_string())).await?
+        }
+        "lastsave" => {
+            let last_save = lock(storage, &mut session.lock_wait).await.snapshot.last_save_unix;
+            handler.response(Integer(None, last_save)).await?
+        }
+        c => {
+            handler.response(SimpleError(AppError::UnknownCommand(c.to_string()).to_string())).await?;
+        }
     }
+    Ok(())
 }
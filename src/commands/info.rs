@@ -0,0 +1,91 @@
+use std::fs;
+use crate::config::info_server::InfoServer;
+use crate::storage::Storage;
+use crate::utils::units::format_memory_human;
+
+/// Sections returned by a bare INFO or `INFO default`.
+const DEFAULT_SECTIONS: &[&str] = &["server", "clients", "memory", "persistence", "stats", "replication", "cpu", "errorstats", "keyspace"];
+
+/// Every section, in the order they are printed.
+const ALL_SECTIONS: &[&str] = &[
+    "server", "clients", "memory", "persistence", "stats", "replication", "cpu",
+    "commandstats", "errorstats", "latencystats", "keyspace",
+];
+
+/// Builds the INFO reply for the requested sections. Unknown section names are ignored.
+pub fn info(args: &[String], storage: &Storage, info_server: &InfoServer) -> String {
+    let mut requested: Vec<&str> = Vec::new();
+    if args.is_empty() {
+        requested.extend(DEFAULT_SECTIONS);
+    }
+    for arg in args {
+        match arg.to_lowercase().as_str() {
+            "all" | "everything" => requested.extend(ALL_SECTIONS),
+            "default" => requested.extend(DEFAULT_SECTIONS),
+            name => requested.extend(ALL_SECTIONS.iter().find(|section| **section == name)),
+        }
+    }
+
+    ALL_SECTIONS
+        .iter()
+        .filter(|section| requested.contains(section))
+        .map(|section| {
+            let fields = match *section {
+                "server" => info_server.server_info(),
+                "clients" => info_server.clients_info(),
+                "memory" => memory_info(storage),
+                "persistence" => storage.persistence_info(),
+                "stats" => stats_info(storage, info_server),
+                "replication" => info_server.replication_info(),
+                "cpu" => info_server.cpu_info(),
+                "commandstats" => info_server.commandstats_info(),
+                "errorstats" => info_server.errorstats_info(),
+                "latencystats" => info_server.latencystats_info(),
+                _ => storage.keyspace_info(),
+            };
+            format_section(section, &fields)
+        })
+        .collect::<Vec<String>>()
+        .join("\r\n")
+}
+
+fn format_section(name: &str, fields: &[(String, String)]) -> String {
+    let title = match name {
+        "cpu" => "CPU".to_string(),
+        _ => format!("{}{}", name[..1].to_uppercase(), &name[1..]),
+    };
+    let mut section = format!("# {}\r\n", title);
+    for (field, value) in fields {
+        section.push_str(&format!("{}:{}\r\n", field, value));
+    }
+    section
+}
+
+fn memory_info(storage: &Storage) -> Vec<(String, String)> {
+    let used_memory = storage.used_memory();
+    let maxmemory = storage.config.maxmemory;
+    vec![
+        ("used_memory".to_string(), used_memory.to_string()),
+        ("used_memory_human".to_string(), format_memory_human(used_memory)),
+        ("used_memory_rss".to_string(), resident_set_size().unwrap_or_default().to_string()),
+        ("maxmemory".to_string(), maxmemory.to_string()),
+        ("maxmemory_human".to_string(), format_memory_human(maxmemory)),
+        ("maxmemory_policy".to_string(), "noeviction".to_string()),
+    ]
+}
+
+fn stats_info(storage: &Storage, info_server: &InfoServer) -> Vec<(String, String)> {
+    let mut fields = info_server.stats_info();
+    fields.push(("expired_keys".to_string(), storage.stats.expired_keys.to_string()));
+    fields.push(("evicted_keys".to_string(), "0".to_string()));
+    fields.push(("keyspace_hits".to_string(), storage.stats.hits.to_string()));
+    fields.push(("keyspace_misses".to_string(), storage.stats.misses.to_string()));
+    fields
+}
+
+/// Resident memory from `/proc/self/statm`, assuming 4 KiB pages.
+fn resident_set_size() -> Option<u64> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(pages * 4096)
+}
@@ -1 +1,10 @@
-pub mod handler;
\ No newline at end of file
+pub mod handler;
+pub mod geo;
+pub mod config;
+pub mod info;
+pub mod table;
+pub mod slowlog;
+pub mod monitor;
+pub mod client;
+pub mod auth;
+pub mod acl;
@@ -0,0 +1,28 @@
+use std::io::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::watch;
+use crate::resp::handler::{ClientStream, RespHandler};
+use crate::types::client_registry::until_killed;
+use crate::resp::parser::Parser::SimpleString;
+
+/// Streams every command fed by other connections until the client quits, disconnects or is killed.
+/// Other commands sent by the monitoring client itself are ignored.
+pub async fn monitor<S: ClientStream>(handler: &mut RespHandler<S>, mut feed: Receiver<String>, killed: &mut watch::Receiver<bool>) -> Result<(), Error> {
+    loop {
+        tokio::select! {
+            _ = until_killed(killed) => return Ok(()),
+            line = feed.recv() => match line {
+                Ok(line) => handler.response(SimpleString(line)).await?,
+                Err(RecvError::Lagged(skipped)) => log_warning!("MONITOR client fell behind, {} commands skipped", skipped),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            received = handler.get_command_with_args() => {
+                let (command, _) = received?;
+                if command == "quit" {
+                    return handler.response(SimpleString("OK".to_string())).await;
+                }
+            }
+        }
+    }
+}
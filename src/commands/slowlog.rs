@@ -0,0 +1,46 @@
+use crate::errors::app_errors::AppError;
+use crate::resp::parser::Parser;
+use crate::resp::parser::Parser::{Array, BulkString, Integer, NestedArray, SimpleString};
+use crate::types::slow_log::SlowLog;
+
+/// Entries returned by SLOWLOG GET when no count is given.
+const DEFAULT_GET_COUNT: usize = 10;
+
+pub fn slowlog(args: &[String], slow_log: &mut SlowLog) -> Result<Parser, AppError> {
+    let Some(subcommand) = args.first() else {
+        return Err(AppError::WrongNumberOfArgumentsError);
+    };
+
+    match subcommand.to_lowercase().as_str() {
+        "get" => {
+            let count = match args.get(1) {
+                None => Some(DEFAULT_GET_COUNT),
+                Some(count) => match count.parse::<i64>() {
+                    Ok(-1) => None,
+                    Ok(count) if count >= 0 => Some(count as usize),
+                    Ok(_) => return Err(AppError::IncompatibleOptions("count should be greater than or equal to -1")),
+                    Err(_) => return Err(AppError::InvalidInteger),
+                },
+            };
+
+            let entries = slow_log
+                .get(count)
+                .map(|entry| NestedArray(vec![
+                    Integer(None, entry.id),
+                    Integer(None, entry.timestamp),
+                    Integer(None, entry.duration),
+                    Array(entry.args.clone()),
+                    BulkString(entry.client_addr.clone()),
+                    BulkString(entry.client_name.clone()),
+                ]))
+                .collect();
+            Ok(NestedArray(entries))
+        }
+        "len" => Ok(Integer(None, slow_log.get(None).count() as u64)),
+        "reset" => {
+            slow_log.reset();
+            Ok(SimpleString("OK".to_string()))
+        }
+        _ => Err(AppError::UnknownSubcommand(subcommand.clone())),
+    }
+}
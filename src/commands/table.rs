@@ -0,0 +1,132 @@
+/// Administrative command, hidden from MONITOR.
+pub const ADMIN: u8 = 1 << 0;
+/// Command that may modify the dataset, held back by CLIENT PAUSE WRITE.
+pub const WRITE: u8 = 1 << 1;
+/// Command allowed before the connection authenticates.
+pub const NO_AUTH: u8 = 1 << 2;
+/// Command whose arguments may carry credentials, redacted in MONITOR and the slow log.
+pub const SENSITIVE: u8 = 1 << 3;
+
+/// Key argument that is read.
+pub const KEY_READ: u8 = 1 << 0;
+/// Key argument that is written or deleted.
+pub const KEY_WRITE: u8 = 1 << 1;
+
+/// Every ACL category, in the order ACL CAT lists them.
+pub static ACL_CATEGORIES: &[&str] = &[
+    "keyspace", "read", "write", "set", "sortedset", "list", "hash", "string", "bitmap", "hyperloglog",
+    "geo", "stream", "pubsub", "admin", "fast", "slow", "blocking", "dangerous", "connection",
+    "transaction", "scripting",
+];
+
+/// Positions of key arguments, counting the command name as position 0.
+#[derive(Debug)]
+pub struct KeySpec {
+    pub first: usize,
+    /// Last key position. Negative values count from the end, `-1` being the last argument.
+    pub last: i32,
+    pub step: usize,
+    pub access: u8,
+}
+
+impl KeySpec {
+    const fn single(position: usize, access: u8) -> Self {
+        KeySpec { first: position, last: position as i32, step: 1, access }
+    }
+}
+
+/// Static description of a command, checked before it is dispatched.
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Number of arguments including the command name. A negative arity `-n` means at least `n`.
+    pub arity: i32,
+    pub flags: u8,
+    /// ACL categories that do not follow from the flags or key specs.
+    pub categories: &'static [&'static str],
+    pub keys: &'static [KeySpec],
+}
+
+impl CommandSpec {
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Whether `argc` arguments, not counting the command name, satisfy the arity.
+    pub fn accepts(&self, argc: usize) -> bool {
+        let argc = argc as i32 + 1;
+        match self.arity {
+            arity if arity >= 0 => argc == arity,
+            arity => argc >= -arity,
+        }
+    }
+
+    /// Whether the command belongs to an ACL category. `write`, `admin` and `dangerous` follow
+    /// from the flags, `read` from read-only key arguments, and commands not marked `fast` are `slow`.
+    pub fn in_category(&self, category: &str) -> bool {
+        self.categories.contains(&category)
+            || match category {
+                "write" => self.has_flag(WRITE),
+                "admin" | "dangerous" => self.has_flag(ADMIN),
+                "read" => !self.has_flag(WRITE) && self.keys.iter().any(|spec| spec.access & KEY_READ != 0),
+                "slow" => !self.categories.contains(&"fast"),
+                _ => false,
+            }
+    }
+
+    /// Key arguments of a call as `(key, access)` pairs. `args` excludes the command name.
+    pub fn key_args<'a>(&self, args: &'a [String]) -> Vec<(&'a str, u8)> {
+        let argc = args.len() as i32 + 1;
+        let mut keys = Vec::new();
+        for spec in self.keys {
+            let last = if spec.last < 0 { argc + spec.last } else { spec.last };
+            let mut position = spec.first as i32;
+            while position <= last && position < argc {
+                keys.push((args[position as usize - 1].as_str(), spec.access));
+                position += spec.step as i32;
+            }
+        }
+        keys
+    }
+}
+
+pub static COMMANDS: &[CommandSpec] = &[
+    CommandSpec { name: "ping", arity: -1, flags: 0, categories: &["fast", "connection"], keys: &[] },
+    CommandSpec { name: "echo", arity: 2, flags: 0, categories: &["fast", "connection"], keys: &[] },
+    CommandSpec { name: "auth", arity: -2, flags: NO_AUTH | SENSITIVE, categories: &["fast", "connection"], keys: &[] },
+    CommandSpec { name: "hello", arity: -1, flags: NO_AUTH | SENSITIVE, categories: &["fast", "connection"], keys: &[] },
+    CommandSpec { name: "quit", arity: -1, flags: NO_AUTH, categories: &["fast", "connection"], keys: &[] },
+    CommandSpec { name: "set", arity: -3, flags: WRITE, categories: &["string"], keys: &[KeySpec::single(1, KEY_WRITE)] },
+    CommandSpec { name: "get", arity: 2, flags: 0, categories: &["string", "fast"], keys: &[KeySpec::single(1, KEY_READ)] },
+    CommandSpec { name: "del", arity: -2, flags: WRITE, categories: &["keyspace"], keys: &[KeySpec { first: 1, last: -1, step: 1, access: KEY_WRITE }] },
+    CommandSpec { name: "keys", arity: 2, flags: 0, categories: &["keyspace", "read", "dangerous"], keys: &[] },
+    CommandSpec { name: "info", arity: -1, flags: 0, categories: &["dangerous"], keys: &[] },
+    CommandSpec { name: "config", arity: -2, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "slowlog", arity: -2, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "monitor", arity: 1, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "client", arity: -2, flags: ADMIN, categories: &["connection"], keys: &[] },
+    CommandSpec { name: "acl", arity: -2, flags: ADMIN | SENSITIVE, categories: &[], keys: &[] },
+    CommandSpec { name: "replconf", arity: -1, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "psync", arity: -1, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "save", arity: 1, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "bgsave", arity: -1, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "bgrewriteaof", arity: 1, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "shutdown", arity: -1, flags: ADMIN, categories: &[], keys: &[] },
+    CommandSpec { name: "lastsave", arity: 1, flags: 0, categories: &["admin", "fast", "dangerous"], keys: &[] },
+    CommandSpec { name: "geoadd", arity: -5, flags: WRITE, categories: &["geo"], keys: &[KeySpec::single(1, KEY_WRITE)] },
+    CommandSpec { name: "geopos", arity: -2, flags: 0, categories: &["geo"], keys: &[KeySpec::single(1, KEY_READ)] },
+    CommandSpec { name: "geodist", arity: -4, flags: 0, categories: &["geo"], keys: &[KeySpec::single(1, KEY_READ)] },
+    CommandSpec { name: "geohash", arity: -2, flags: 0, categories: &["geo"], keys: &[KeySpec::single(1, KEY_READ)] },
+    CommandSpec { name: "geosearch", arity: -7, flags: 0, categories: &["geo"], keys: &[KeySpec::single(1, KEY_READ)] },
+    CommandSpec {
+        name: "geosearchstore",
+        arity: -8,
+        flags: WRITE,
+        categories: &["geo"],
+        keys: &[KeySpec::single(1, KEY_WRITE), KeySpec::single(2, KEY_READ)],
+    },
+];
+
+pub fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
@@ -0,0 +1,92 @@
+use crate::utils::units::parse_memory;
+
+/// The classes `client-output-buffer-limit` sets limits for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClientClass {
+    Normal,
+    Replica,
+    PubSub,
+}
+
+impl ClientClass {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "normal" => Some(ClientClass::Normal),
+            "replica" | "slave" => Some(ClientClass::Replica),
+            "pubsub" => Some(ClientClass::PubSub),
+            _ => None,
+        }
+    }
+}
+
+/// A client is disconnected once its pending output goes over `hard` bytes, or stays over
+/// `soft` bytes for `soft_seconds`. A limit of 0 is disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OutputBufferLimit {
+    pub hard: u64,
+    pub soft: u64,
+    pub soft_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputBufferLimits {
+    pub normal: OutputBufferLimit,
+    pub replica: OutputBufferLimit,
+    pub pubsub: OutputBufferLimit,
+}
+
+impl OutputBufferLimits {
+    pub fn get(&self, class: ClientClass) -> OutputBufferLimit {
+        match class {
+            ClientClass::Normal => self.normal,
+            ClientClass::Replica => self.replica,
+            ClientClass::PubSub => self.pubsub,
+        }
+    }
+
+    fn get_mut(&mut self, class: ClientClass) -> &mut OutputBufferLimit {
+        match class {
+            ClientClass::Normal => &mut self.normal,
+            ClientClass::Replica => &mut self.replica,
+            ClientClass::PubSub => &mut self.pubsub,
+        }
+    }
+}
+
+impl Default for OutputBufferLimits {
+    fn default() -> Self {
+        OutputBufferLimits {
+            normal: OutputBufferLimit::default(),
+            replica: OutputBufferLimit { hard: 256 * 1024 * 1024, soft: 64 * 1024 * 1024, soft_seconds: 60 },
+            pubsub: OutputBufferLimit { hard: 32 * 1024 * 1024, soft: 8 * 1024 * 1024, soft_seconds: 60 },
+        }
+    }
+}
+
+/// Parses `"<class> <hard> <soft> <soft seconds> ..."` groups over `current`, so classes left
+/// out keep their limits.
+pub fn parse_output_buffer_limits(current: &OutputBufferLimits, value: &str) -> Result<OutputBufferLimits, String> {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    if words.is_empty() || !words.len().is_multiple_of(4) {
+        return Err("Wrong number of arguments in buffer limit configuration.".to_string());
+    }
+
+    let mut limits = *current;
+    for group in words.chunks(4) {
+        let class = ClientClass::from_name(group[0])
+            .ok_or_else(|| format!("Invalid client class specified in buffer limit configuration: {}", group[0]))?;
+        let (Some(hard), Some(soft), Ok(soft_seconds)) = (parse_memory(group[1]), parse_memory(group[2]), group[3].parse::<u64>()) else {
+            return Err("Error in hard, soft or soft_seconds setting in buffer limit configuration.".to_string());
+        };
+        *limits.get_mut(class) = OutputBufferLimit { hard, soft, soft_seconds };
+    }
+    Ok(limits)
+}
+
+pub fn format_output_buffer_limits(limits: &OutputBufferLimits) -> String {
+    [("normal", limits.normal), ("slave", limits.replica), ("pubsub", limits.pubsub)]
+        .iter()
+        .map(|(name, limit)| format!("{} {} {} {}", name, limit.hard, limit.soft, limit.soft_seconds))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -0,0 +1,314 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use crate::config::registry::{find_parameter, ConfigKind, ConfigParameter, PARAMETERS};
+use crate::config::server_config::ServerConfig;
+use crate::errors::app_errors::AppError;
+use crate::utils::atomic_file::write_atomically;
+use crate::utils::repr::quote_repr;
+
+/// Nested `include` directives deeper than this are assumed to be a cycle.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Marks where CONFIG REWRITE appends parameters that were not in the file before.
+const REWRITE_SIGNATURE: &str = "# Generated by CONFIG REWRITE";
+
+/// A single non-empty directive, remembering where it came from for error reporting.
+#[derive(Debug, Clone)]
+pub struct ConfigLine {
+    pub source: String,
+    pub number: usize,
+    pub content: String,
+    pub args: Vec<String>,
+}
+
+impl ConfigLine {
+    pub fn error(&self, reason: &str) -> AppError {
+        AppError::ConfigFileError(self.source.clone(), self.number, self.content.clone(), reason.to_string())
+    }
+}
+
+/// Splits a line into arguments the way Redis does: whitespace separated, with double quoted
+/// strings supporting `\n`, `\r`, `\t`, `\b`, `\a`, `\\`, `\"` and `\xHH` escapes, and single
+/// quoted strings supporting only `\'`. Returns `None` on unbalanced quotes.
+pub fn split_args(line: &str) -> Option<Vec<String>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i == chars.len() {
+            return Some(args);
+        }
+
+        let mut current = String::new();
+        let mut in_double = false;
+        let mut in_single = false;
+        loop {
+            if in_double {
+                let c = *chars.get(i)?;
+                if c == '\\' && i + 3 < chars.len() && chars[i + 1] == 'x'
+                    && chars[i + 2].is_ascii_hexdigit() && chars[i + 3].is_ascii_hexdigit() {
+                    let hex: String = chars[i + 2..i + 4].iter().collect();
+                    current.push(u8::from_str_radix(&hex, 16).ok()? as char);
+                    i += 3;
+                } else if c == '\\' && i + 1 < chars.len() {
+                    i += 1;
+                    current.push(match chars[i] {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'a' => '\u{7}',
+                        other => other,
+                    });
+                } else if c == '"' {
+                    // The closing quote must be followed by a space or end the line
+                    if i + 1 < chars.len() && !chars[i + 1].is_whitespace() {
+                        return None;
+                    }
+                    i += 1;
+                    break;
+                } else {
+                    current.push(c);
+                }
+            } else if in_single {
+                let c = *chars.get(i)?;
+                if c == '\\' && chars.get(i + 1) == Some(&'\'') {
+                    i += 1;
+                    current.push('\'');
+                } else if c == '\'' {
+                    if i + 1 < chars.len() && !chars[i + 1].is_whitespace() {
+                        return None;
+                    }
+                    i += 1;
+                    break;
+                } else {
+                    current.push(c);
+                }
+            } else {
+                match chars.get(i) {
+                    None => break,
+                    Some(c) if c.is_whitespace() => break,
+                    Some('"') => in_double = true,
+                    Some('\'') => in_single = true,
+                    Some(c) => current.push(*c),
+                }
+            }
+            i += 1;
+        }
+        args.push(current);
+    }
+}
+
+/// Reads every directive in `path`, expanding `include` directives in place.
+pub fn read_config_file(path: &Path) -> Result<Vec<ConfigLine>, AppError> {
+    let mut lines = Vec::new();
+    read_into(path, 0, &mut lines)?;
+    Ok(lines)
+}
+
+fn read_into(path: &Path, depth: usize, lines: &mut Vec<ConfigLine>) -> Result<(), AppError> {
+    let source = path.display().to_string();
+    let content = fs::read_to_string(path).map_err(AppError::FileError)?;
+
+    for (index, raw) in content.lines().enumerate() {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let mut line = ConfigLine {
+            source: source.clone(),
+            number: index + 1,
+            content: trimmed.to_string(),
+            args: Vec::new(),
+        };
+        line.args = split_args(trimmed).ok_or_else(|| line.error("Unbalanced quotes in configuration line"))?;
+
+        if line.args[0].eq_ignore_ascii_case("include") {
+            if line.args.len() != 2 {
+                return Err(line.error("wrong number of arguments"));
+            }
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(line.error("include nesting is too deep"));
+            }
+            read_into(Path::new(&line.args[1]), depth + 1, lines).map_err(|e| match e {
+                AppError::FileError(e) => line.error(&format!("Can't open included file: {}", e)),
+                other => other,
+            })?;
+            continue;
+        }
+        lines.push(line);
+    }
+    Ok(())
+}
+
+/// Quotes `value` if it would not survive `split_args` as a single bare argument.
+fn quote_arg(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value.chars().all(|c| c.is_ascii_graphic() && !matches!(c, '"' | '\'' | '\\'));
+    match bare {
+        true => value.to_string(),
+        false => quote_repr(value),
+    }
+}
+
+/// Formats the current value of `parameter` as a config file line, or `None` when the line
+/// should be left out because the parameter is unset.
+fn format_directive(parameter: &ConfigParameter, config: &ServerConfig) -> Option<String> {
+    let value = parameter.get(config);
+    if value.is_empty() && parameter.default.is_empty() {
+        return None;
+    }
+
+    let args = match parameter.kind {
+        ConfigKind::List if !value.is_empty() => value.split_whitespace().map(quote_arg).collect::<Vec<String>>().join(" "),
+        _ => quote_arg(&value),
+    };
+    Some(format!("{} {}", parameter.name, args))
+}
+
+/// Rewrites the config file at `path` to match `config`. Comments, blank lines and unknown
+/// directives are kept, known directives are updated in place and duplicates are dropped.
+/// Parameters that differ from their default but are not in the file yet are appended.
+pub fn rewrite_config_file(path: &Path, config: &ServerConfig) -> Result<(), AppError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(AppError::FileError(e)),
+    };
+
+    let mut output = Vec::new();
+    let mut written: Vec<&str> = Vec::new();
+    for raw in content.lines() {
+        let parameter = split_args(raw.trim())
+            .and_then(|args| args.first().and_then(|name| find_parameter(name)));
+        match parameter {
+            Some(parameter) if written.contains(&parameter.name) => {}
+            Some(parameter) => {
+                written.push(parameter.name);
+                output.extend(format_directive(parameter, config));
+            }
+            None => output.push(raw.to_string()),
+        }
+    }
+
+    let missing: Vec<String> = PARAMETERS
+        .iter()
+        .filter(|parameter| !written.contains(&parameter.name) && !parameter.is_default(config))
+        .filter_map(|parameter| format_directive(parameter, config))
+        .collect();
+    if !missing.is_empty() && !output.iter().any(|line| line == REWRITE_SIGNATURE) {
+        output.push(REWRITE_SIGNATURE.to_string());
+    }
+    output.extend(missing);
+
+    write_atomically(path, |writer| {
+        for line in &output {
+            writeln!(writer, "{}", line).map_err(AppError::FileError)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn args(line: &str) -> Option<Vec<String>> {
+        split_args(line)
+    }
+
+    /// A scratch directory per test, removed when the test ends.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("config-file-test-{}-{}", std::process::id(), name));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn write(&self, name: &str, content: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn splits_bare_and_quoted_arguments() {
+        assert_eq!(args("  save   900 1 ").unwrap(), ["save", "900", "1"]);
+        assert_eq!(args("dbfilename \"my dump.rdb\"").unwrap(), ["dbfilename", "my dump.rdb"]);
+        assert_eq!(args("requirepass 'it\\'s'").unwrap(), ["requirepass", "it's"]);
+        assert_eq!(args("a \"tab\\there\\n\" \"\\x41\\x7a\"").unwrap(), ["a", "tab\there\n", "Az"]);
+        assert_eq!(args("save \"\"").unwrap(), ["save", ""]);
+        assert_eq!(args("single '\\n stays'").unwrap(), ["single", "\\n stays"]);
+        assert!(args("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unbalanced_or_glued_quotes() {
+        assert_eq!(args("dir \"/tmp"), None);
+        assert_eq!(args("dir '/tmp"), None);
+        assert_eq!(args("dir \"/tmp\"x"), None);
+        assert_eq!(args("dir '/tmp'x"), None);
+    }
+
+    #[test]
+    fn reads_directives_and_expands_includes() {
+        let dir = TempDir::new("include");
+        let included = dir.write("extra.conf", "# included\nport 7000\n");
+        let main = dir.write(
+            "redis.conf",
+            &format!("# comment\n\nbind 127.0.0.1\ninclude {}\n  dbfilename \"a b.rdb\"\n", included.display()),
+        );
+
+        let lines = read_config_file(&main).unwrap();
+        let summary: Vec<(usize, Vec<String>)> = lines.iter().map(|line| (line.number, line.args.clone())).collect();
+        assert_eq!(summary, [
+            (3, vec!["bind".to_string(), "127.0.0.1".to_string()]),
+            (2, vec!["port".to_string(), "7000".to_string()]),
+            (5, vec!["dbfilename".to_string(), "a b.rdb".to_string()]),
+        ]);
+        assert_eq!(lines[1].source, included.display().to_string());
+        assert_eq!(lines[2].content, "dbfilename \"a b.rdb\"");
+    }
+
+    #[test]
+    fn reports_bad_lines_and_includes() {
+        let dir = TempDir::new("errors");
+        let unbalanced = dir.write("unbalanced.conf", "port 1\ndir \"/tmp\n");
+        assert!(matches!(read_config_file(&unbalanced), Err(AppError::ConfigFileError(_, 2, _, _))));
+
+        let missing = dir.write("missing.conf", "include /nonexistent/redis.conf\n");
+        let Err(AppError::ConfigFileError(_, 1, _, reason)) = read_config_file(&missing) else { panic!("expected an error") };
+        assert!(reason.starts_with("Can't open included file"));
+
+        let cycle = dir.0.join("cycle.conf");
+        fs::write(&cycle, format!("include {}\n", cycle.display())).unwrap();
+        let Err(AppError::ConfigFileError(_, _, _, reason)) = read_config_file(&cycle) else { panic!("expected an error") };
+        assert_eq!(reason, "include nesting is too deep");
+    }
+
+    #[test]
+    fn quotes_only_what_needs_it() {
+        assert_eq!(quote_arg("plain"), "plain");
+        for value in ["", "two words", "quote\"d", "new\nline"] {
+            let quoted = quote_arg(value);
+            assert_ne!(quoted, value);
+            assert_eq!(split_args(&quoted).unwrap(), [value]);
+        }
+    }
+}
@@ -1,42 +1,277 @@
+use std::collections::BTreeMap;
+use std::fs;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::acl::Acl;
 use crate::config::server_config::ServerConfig;
 use rand::Rng;
 use rand::distr::Alphanumeric;
+use tokio::sync::{broadcast, watch};
+use crate::constants::{MONITOR_BACKLOG, REDIS_VERSION};
 use crate::enums::role::Role;
+use crate::resp::handler::IoCounters;
+use crate::types::histogram::Histogram;
+use crate::storage::unix_time_micros;
+use crate::types::client_registry::ClientRegistry;
+use crate::types::slow_log::SlowLog;
+use crate::utils::repr::quote_repr;
+
+/// Percentiles reported for every command in the Latencystats section.
+const LATENCY_PERCENTILES: [f64; 3] = [50.0, 99.0, 99.9];
+
+/// How a command left the dispatch path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandOutcome {
+    /// Not a known command, so it is not tracked per command.
+    Unknown,
+    /// Refused before execution, e.g. because of a wrong number of arguments.
+    Rejected,
+    Executed,
+}
+
+/// Per-command counters reported in the Commandstats and Latencystats sections of INFO.
+#[derive(Debug, Default)]
+pub struct CommandStats {
+    pub calls: u64,
+    pub usec: u64,
+    pub rejected_calls: u64,
+    pub failed_calls: u64,
+    /// Execution times in nanoseconds.
+    pub latency: Histogram,
+}
+
+/// Server wide counters reported in the Clients and Stats sections of INFO.
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    pub total_connections_received: u64,
+    pub rejected_connections: u64,
+    pub total_commands_processed: u64,
+    pub total_net_input_bytes: u64,
+    pub total_net_output_bytes: u64,
+    pub total_error_replies: u64,
+    /// Failed AUTH and HELLO AUTH attempts.
+    pub acl_access_denied_auth: u64,
+    /// Commands refused because the user may not run them.
+    pub acl_access_denied_cmd: u64,
+    /// Commands refused because the user may not access one of their keys.
+    pub acl_access_denied_key: u64,
+    /// Error replies by their prefix, e.g. `ERR` or `WRONGTYPE`.
+    pub error_replies: BTreeMap<String, u64>,
+    pub commands: BTreeMap<String, CommandStats>,
+}
+
+impl ServerStats {
+    pub fn record_command(&mut self, command: &str, outcome: CommandOutcome, duration: Duration, counters: IoCounters) {
+        let failed = !counters.error_prefixes.is_empty();
+        match outcome {
+            CommandOutcome::Unknown => {}
+            CommandOutcome::Rejected => self.commands.entry(command.to_string()).or_default().rejected_calls += 1,
+            CommandOutcome::Executed => {
+                self.total_commands_processed += 1;
+                let stats = self.commands.entry(command.to_string()).or_default();
+                stats.calls += 1;
+                stats.usec += duration.as_micros() as u64;
+                stats.failed_calls += failed as u64;
+                stats.latency.record(duration.as_nanos() as u64);
+            }
+        }
+
+        self.total_net_input_bytes += counters.read;
+        self.total_net_output_bytes += counters.written;
+        for prefix in counters.error_prefixes {
+            self.total_error_replies += 1;
+            *self.error_replies.entry(prefix).or_default() += 1;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = ServerStats::default();
+    }
+}
 
 #[derive(Debug)]
 pub struct InfoServer {
-    role: Role,
+    pub(crate) role: Role,
+    config: Arc<ServerConfig>,
+    run_id: String,
+    started: Instant,
     pub(crate) connected_slaves: u16,
     pub(crate) master_replid: String,
     pub(crate) master_repl_offset: u16,
+    pub(crate) stats: ServerStats,
+    pub(crate) slow_log: SlowLog,
+    pub(crate) clients: ClientRegistry,
+    pub(crate) acl: Acl,
+    /// Feed of executed commands for MONITOR clients.
+    pub(crate) monitors: broadcast::Sender<String>,
+    /// Set while a shutdown is in progress, which stops new clients from being accepted.
+    pub(crate) shutting_down: watch::Sender<bool>,
 }
 
 impl InfoServer {
     pub fn new(config: Arc<ServerConfig>) -> Self {
+        let mut clients = ClientRegistry::new();
+        clients.configure(&config);
         InfoServer {
             role: match config.is_replication {
                 true => Role::Slave,
                 false => Role::Master,
             },
+            run_id: get_random_replid(),
+            started: Instant::now(),
             connected_slaves: 0,
             master_replid: get_random_replid(),
             master_repl_offset: 0,
+            stats: ServerStats::default(),
+            slow_log: SlowLog::new(&config),
+            clients,
+            acl: Acl::new(&config),
+            monitors: broadcast::channel(MONITOR_BACKLOG).0,
+            shutting_down: watch::channel(false).0,
+            config,
         }
     }
 
-    pub fn get_info_string(&mut self) -> String {
-        format!(
-            "# {}\nrole:{}\nconnected_slaves:{}\nmaster_replid:{}\nmaster_repl_offset:{}",
-            "Replication",
-            self.role,
-            self.connected_slaves,
-            self.master_replid,
-            self.master_repl_offset
-        )
+    /// Sends a command to attached MONITOR clients, formatted like `+<time> [0 <addr>] "cmd" "arg"`.
+    pub fn feed_monitors(&self, command: &str, args: &[String], client_addr: &str) {
+        if self.monitors.receiver_count() == 0 {
+            return;
+        }
+
+        let now = unix_time_micros();
+        let mut line = format!("{}.{:06} [0 {}]", now / 1_000_000, now % 1_000_000, client_addr);
+        for arg in std::iter::once(command).chain(args.iter().map(String::as_str)) {
+            line.push(' ');
+            line.push_str(&quote_repr(arg));
+        }
+        // Sending only fails when every monitor disconnected in the meantime
+        let _ = self.monitors.send(line);
+    }
+
+    /// Picks up settings changed through CONFIG SET.
+    pub fn apply_config(&mut self, config: &ServerConfig) {
+        self.slow_log.configure(config);
+        self.clients.configure(config);
+        self.acl.apply_config(config);
+    }
+
+    pub fn server_info(&self) -> Vec<(String, String)> {
+        let uptime = self.started.elapsed().as_secs();
+        let executable = std::env::current_exe().map(|path| path.display().to_string()).unwrap_or_default();
+        let config_file = self.config.config_file.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+
+        to_fields(vec![
+            ("redis_version", REDIS_VERSION.to_string()),
+            ("redis_mode", "standalone".to_string()),
+            ("os", format!("{} {}", std::env::consts::OS, std::env::consts::ARCH)),
+            ("arch_bits", usize::BITS.to_string()),
+            ("process_id", std::process::id().to_string()),
+            ("run_id", self.run_id.clone()),
+            ("tcp_port", self.config.port.to_string()),
+            ("uptime_in_seconds", uptime.to_string()),
+            ("uptime_in_days", (uptime / 86400).to_string()),
+            ("executable", executable),
+            ("config_file", config_file),
+        ])
+    }
+
+    pub fn clients_info(&self) -> Vec<(String, String)> {
+        to_fields(vec![
+            ("connected_clients", self.clients.iter().count().to_string()),
+            ("maxclients", self.clients.max_clients().to_string()),
+            ("client_recent_max_input_buffer", self.clients.iter().map(|client| client.query_buffer).max().unwrap_or(0).to_string()),
+            ("client_recent_max_output_buffer", self.clients.iter().map(|client| client.output_buffer).max().unwrap_or(0).to_string()),
+            ("blocked_clients", self.clients.iter().filter(|client| client.blocked).count().to_string()),
+        ])
+    }
+
+    pub fn stats_info(&self) -> Vec<(String, String)> {
+        to_fields(vec![
+            ("total_connections_received", self.stats.total_connections_received.to_string()),
+            ("total_commands_processed", self.stats.total_commands_processed.to_string()),
+            ("total_net_input_bytes", self.stats.total_net_input_bytes.to_string()),
+            ("total_net_output_bytes", self.stats.total_net_output_bytes.to_string()),
+            ("rejected_connections", self.stats.rejected_connections.to_string()),
+            ("total_error_replies", self.stats.total_error_replies.to_string()),
+            ("acl_access_denied_auth", self.stats.acl_access_denied_auth.to_string()),
+            ("acl_access_denied_cmd", self.stats.acl_access_denied_cmd.to_string()),
+            ("acl_access_denied_key", self.stats.acl_access_denied_key.to_string()),
+        ])
+    }
+
+    pub fn errorstats_info(&self) -> Vec<(String, String)> {
+        self.stats.error_replies
+            .iter()
+            .map(|(prefix, count)| (format!("errorstat_{}", prefix), format!("count={}", count)))
+            .collect()
+    }
+
+    pub fn commandstats_info(&self) -> Vec<(String, String)> {
+        self.stats.commands
+            .iter()
+            .map(|(command, stats)| {
+                let usec_per_call = if stats.calls > 0 { stats.usec as f64 / stats.calls as f64 } else { 0.0 };
+                (
+                    format!("cmdstat_{}", command),
+                    format!(
+                        "calls={},usec={},usec_per_call={:.2},rejected_calls={},failed_calls={}",
+                        stats.calls, stats.usec, usec_per_call, stats.rejected_calls, stats.failed_calls
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    pub fn latencystats_info(&self) -> Vec<(String, String)> {
+        self.stats.commands
+            .iter()
+            .filter(|(_, stats)| stats.calls > 0)
+            .map(|(command, stats)| {
+                let percentiles = LATENCY_PERCENTILES
+                    .iter()
+                    .map(|p| format!("p{}={:.3}", p, stats.latency.percentile(*p) as f64 / 1000.0))
+                    .collect::<Vec<String>>()
+                    .join(",");
+                (format!("latency_percentiles_usec_{}", command), percentiles)
+            })
+            .collect()
+    }
+
+    pub fn replication_info(&self) -> Vec<(String, String)> {
+        let mut fields = vec![("role", self.role.to_string())];
+        if self.config.is_replication {
+            fields.push(("master_host", self.config.master_host.clone()));
+            fields.push(("master_port", self.config.master_port.to_string()));
+        }
+        fields.push(("connected_slaves", self.connected_slaves.to_string()));
+        fields.push(("master_replid", self.master_replid.clone()));
+        fields.push(("master_repl_offset", self.master_repl_offset.to_string()));
+        to_fields(fields)
+    }
+
+    pub fn cpu_info(&self) -> Vec<(String, String)> {
+        let (user, sys) = process_cpu_seconds().unwrap_or_default();
+        to_fields(vec![
+            ("used_cpu_sys", format!("{:.6}", sys)),
+            ("used_cpu_user", format!("{:.6}", user)),
+        ])
     }
 }
 
+fn to_fields(fields: Vec<(&str, String)>) -> Vec<(String, String)> {
+    fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+}
+
+/// Reads user and system CPU time from `/proc/self/stat`, assuming the usual 100 ticks per second.
+fn process_cpu_seconds() -> Option<(f64, f64)> {
+    let stat = fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name may contain spaces, so fields are counted from its closing parenthesis
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 2..].split(' ').collect();
+    let user = fields.get(11)?.parse::<f64>().ok()?;
+    let sys = fields.get(12)?.parse::<f64>().ok()?;
+    Some((user / 100.0, sys / 100.0))
+}
+
 fn get_random_replid() -> String {
     rand::thread_rng().sample_iter(&Alphanumeric).take(40).map(char::from).collect()
-}
\ No newline at end of file
+}
@@ -1,2 +1,6 @@
 pub mod server_config;
-pub mod info_server;
\ No newline at end of file
+pub mod info_server;
+pub mod save_points;
+pub mod buffer_limits;
+pub mod config_file;
+pub mod registry;
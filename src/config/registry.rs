@@ -0,0 +1,474 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::config::buffer_limits::{format_output_buffer_limits, parse_output_buffer_limits};
+use crate::config::save_points::{format_save_points, parse_save_points};
+use crate::config::server_config::ServerConfig;
+use crate::enums::fsync_policy::FsyncPolicy;
+use crate::logging::syslog::SYSLOG_FACILITIES;
+use crate::logging::{LogFormat, LogLevel};
+use crate::utils::units::parse_memory;
+
+/// How a parameter's value is validated and written back to a config file.
+#[derive(Debug, Clone, Copy)]
+pub enum ConfigKind {
+    Bool,
+    Integer { min: i64, max: i64 },
+    Memory,
+    Enum(&'static [&'static str]),
+    String,
+    /// Space separated list of values, written as several arguments on one line.
+    List,
+}
+
+impl ConfigKind {
+    fn validate(&self, value: &str) -> Result<(), String> {
+        match self {
+            ConfigKind::Bool => parse_bool(value).map(|_| ()),
+            ConfigKind::Integer { min, max } => match value.parse::<i64>() {
+                Ok(n) if n >= *min && n <= *max => Ok(()),
+                Ok(_) => Err(format!("argument must be between {} and {} inclusive", min, max)),
+                Err(_) => Err("argument couldn't be parsed into an integer".to_string()),
+            },
+            ConfigKind::Memory => parse_memory(value).map(|_| ()).ok_or_else(|| "argument must be a memory value".to_string()),
+            ConfigKind::Enum(values) => match values.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+                true => Ok(()),
+                false => Err(format!("argument(s) must be one of the following: {}", values.join(", "))),
+            },
+            ConfigKind::String | ConfigKind::List => Ok(()),
+        }
+    }
+}
+
+pub struct ConfigParameter {
+    pub name: &'static str,
+    pub alias: Option<&'static str>,
+    pub kind: ConfigKind,
+    pub default: &'static str,
+    /// Immutable parameters can only be set from the config file or command line.
+    pub mutable: bool,
+    get: fn(&ServerConfig) -> String,
+    set: fn(&mut ServerConfig, &str) -> Result<(), String>,
+}
+
+impl ConfigParameter {
+    pub fn get(&self, config: &ServerConfig) -> String {
+        (self.get)(config)
+    }
+
+    /// Validates `value` against the parameter's kind and stores it in `config`.
+    pub fn set(&self, config: &mut ServerConfig, value: &str) -> Result<(), String> {
+        self.kind.validate(value)?;
+        (self.set)(config, value)
+    }
+
+    pub fn is_default(&self, config: &ServerConfig) -> bool {
+        self.get(config) == self.default
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err("argument must be 'yes' or 'no'".to_string()),
+    }
+}
+
+/// Parses a memory value that has to be at least 1mb.
+fn parse_buffer_size(value: &str) -> Result<u64, String> {
+    match parse_memory(value) {
+        Some(bytes) if bytes >= 1024 * 1024 => Ok(bytes),
+        _ => Err("argument must be a memory value of at least 1mb".to_string()),
+    }
+}
+
+fn format_bool(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+/// Parses a `bind` entry into the address to listen on and whether it is optional.
+pub fn parse_bind_address(address: &str) -> Option<(IpAddr, bool)> {
+    let (address, optional) = match address.strip_prefix('-') {
+        Some(address) => (address, true),
+        None => (address, false),
+    };
+    let ip = match address {
+        "*" => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        "::*" => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        address => address.parse::<IpAddr>().ok()?,
+    };
+    Some((ip, optional))
+}
+
+// Values reaching the setters were already validated against the parameter kind
+fn parse_validated<T: std::str::FromStr + Default>(value: &str) -> T {
+    value.parse::<T>().unwrap_or_default()
+}
+
+pub static PARAMETERS: &[ConfigParameter] = &[
+    ConfigParameter {
+        name: "port",
+        alias: None,
+        kind: ConfigKind::Integer { min: 0, max: 65535 },
+        default: "6379",
+        mutable: false,
+        get: |c| c.port.to_string(),
+        set: |c, v| { c.port = parse_validated(v); Ok(()) },
+    },
+    ConfigParameter {
+        name: "bind",
+        alias: None,
+        kind: ConfigKind::List,
+        default: "127.0.0.1 -::1",
+        mutable: false,
+        get: |c| c.bind.join(" "),
+        set: |c, v| {
+            let addresses: Vec<String> = v.split_whitespace().map(str::to_string).collect();
+            if addresses.is_empty() {
+                return Err("wrong number of arguments".to_string());
+            }
+            if let Some(invalid) = addresses.iter().find(|address| parse_bind_address(address).is_none()) {
+                return Err(format!("Invalid bind address '{}'", invalid));
+            }
+            c.bind = addresses;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        name: "protected-mode",
+        alias: None,
+        kind: ConfigKind::Bool,
+        default: "yes",
+        mutable: true,
+        get: |c| format_bool(c.protected_mode),
+        set: |c, v| { c.protected_mode = parse_bool(v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "maxclients",
+        alias: None,
+        kind: ConfigKind::Integer { min: 1, max: i64::MAX },
+        default: "10000",
+        mutable: true,
+        get: |c| c.maxclients.to_string(),
+        set: |c, v| { c.maxclients = parse_validated(v); Ok(()) },
+    },
+    ConfigParameter {
+        name: "timeout",
+        alias: None,
+        kind: ConfigKind::Integer { min: 0, max: i64::MAX },
+        default: "0",
+        mutable: true,
+        get: |c| c.timeout.to_string(),
+        set: |c, v| { c.timeout = parse_validated(v); Ok(()) },
+    },
+    ConfigParameter {
+        name: "tcp-keepalive",
+        alias: None,
+        kind: ConfigKind::Integer { min: 0, max: i64::MAX },
+        default: "300",
+        mutable: true,
+        get: |c| c.tcp_keepalive.to_string(),
+        set: |c, v| { c.tcp_keepalive = parse_validated(v); Ok(()) },
+    },
+    ConfigParameter {
+        name: "client-output-buffer-limit",
+        alias: None,
+        kind: ConfigKind::List,
+        default: "normal 0 0 0 slave 268435456 67108864 60 pubsub 33554432 8388608 60",
+        mutable: true,
+        get: |c| format_output_buffer_limits(&c.client_output_buffer_limits),
+        set: |c, v| { c.client_output_buffer_limits = parse_output_buffer_limits(&c.client_output_buffer_limits, v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "client-query-buffer-limit",
+        alias: None,
+        kind: ConfigKind::Memory,
+        default: "1073741824",
+        mutable: true,
+        get: |c| c.client_query_buffer_limit.to_string(),
+        set: |c, v| { c.client_query_buffer_limit = parse_buffer_size(v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "proto-max-bulk-len",
+        alias: None,
+        kind: ConfigKind::Memory,
+        default: "536870912",
+        mutable: true,
+        get: |c| c.proto_max_bulk_len.to_string(),
+        set: |c, v| { c.proto_max_bulk_len = parse_buffer_size(v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "loglevel",
+        alias: None,
+        kind: ConfigKind::Enum(&["debug", "verbose", "notice", "warning", "nothing"]),
+        default: "notice",
+        mutable: true,
+        get: |c| c.loglevel.as_str().to_string(),
+        set: |c, v| { c.loglevel = v.parse::<LogLevel>().map_err(|_| "invalid log level".to_string())?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "logfile",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "",
+        mutable: false,
+        get: |c| c.logfile.clone().unwrap_or_default(),
+        set: |c, v| { c.logfile = (!v.is_empty()).then(|| v.to_string()); Ok(()) },
+    },
+    ConfigParameter {
+        name: "log-format",
+        alias: None,
+        kind: ConfigKind::Enum(&["plain", "json"]),
+        default: "plain",
+        mutable: true,
+        get: |c| c.log_format.as_str().to_string(),
+        set: |c, v| { c.log_format = v.parse::<LogFormat>().map_err(|_| "invalid log format".to_string())?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "syslog-enabled",
+        alias: None,
+        kind: ConfigKind::Bool,
+        default: "no",
+        mutable: false,
+        get: |c| format_bool(c.syslog_enabled),
+        set: |c, v| { c.syslog_enabled = parse_bool(v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "syslog-ident",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "redis",
+        mutable: false,
+        get: |c| c.syslog_ident.clone(),
+        set: |c, v| { c.syslog_ident = v.to_string(); Ok(()) },
+    },
+    ConfigParameter {
+        name: "syslog-facility",
+        alias: None,
+        kind: ConfigKind::Enum(SYSLOG_FACILITIES),
+        default: "local0",
+        mutable: false,
+        get: |c| c.syslog_facility.clone(),
+        set: |c, v| { c.syslog_facility = v.to_lowercase(); Ok(()) },
+    },
+    ConfigParameter {
+        name: "unixsocket",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "",
+        mutable: false,
+        get: |c| c.unixsocket.clone().unwrap_or_default(),
+        set: |c, v| { c.unixsocket = (!v.is_empty()).then(|| v.to_string()); Ok(()) },
+    },
+    ConfigParameter {
+        name: "unixsocketperm",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "0",
+        mutable: false,
+        get: |c| format!("{:o}", c.unixsocketperm),
+        set: |c, v| {
+            c.unixsocketperm = u32::from_str_radix(v, 8)
+                .ok()
+                .filter(|perm| *perm <= 0o777)
+                .ok_or_else(|| "argument must be an octal number between 0 and 777".to_string())?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        name: "replicaof",
+        alias: Some("slaveof"),
+        kind: ConfigKind::List,
+        default: "",
+        mutable: false,
+        get: |c| match c.is_replication {
+            true => format!("{} {}", c.master_host, c.master_port),
+            false => String::new(),
+        },
+        set: |c, v| {
+            let parts: Vec<&str> = v.split_whitespace().collect();
+            match parts.as_slice() {
+                [] => c.is_replication = false,
+                [no, one] if no.eq_ignore_ascii_case("no") && one.eq_ignore_ascii_case("one") => c.is_replication = false,
+                [host, port] => {
+                    c.master_host = host.to_string();
+                    c.master_port = port.parse::<u16>().map_err(|_| "Invalid master port".to_string())?;
+                    c.is_replication = true;
+                }
+                _ => return Err("wrong number of arguments".to_string()),
+            }
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        name: "dir",
+        alias: None,
+        kind: ConfigKind::String,
+        default: ".",
+        mutable: true,
+        get: |c| c.dir.clone(),
+        set: |c, v| { c.dir = v.to_string(); Ok(()) },
+    },
+    ConfigParameter {
+        name: "dbfilename",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "dump.rdb",
+        mutable: true,
+        get: |c| c.dbfilename.clone(),
+        set: |c, v| {
+            if v.contains('/') {
+                return Err("dbfilename can't be a path, just a filename".to_string());
+            }
+            c.dbfilename = v.to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        name: "rdbcompression",
+        alias: None,
+        kind: ConfigKind::Bool,
+        default: "yes",
+        mutable: true,
+        get: |c| format_bool(c.rdbcompression),
+        set: |c, v| { c.rdbcompression = parse_bool(v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "save",
+        alias: None,
+        kind: ConfigKind::List,
+        default: "3600 1 300 100 60 10000",
+        mutable: true,
+        get: |c| format_save_points(&c.save_points),
+        set: |c, v| {
+            c.save_points = parse_save_points(v).map_err(|_| "Invalid save parameters".to_string())?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        name: "appendonly",
+        alias: None,
+        kind: ConfigKind::Bool,
+        default: "no",
+        mutable: false,
+        get: |c| format_bool(c.appendonly),
+        set: |c, v| { c.appendonly = parse_bool(v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "appendfilename",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "appendonly.aof",
+        mutable: false,
+        get: |c| c.appendfilename.clone(),
+        set: |c, v| { c.appendfilename = v.to_string(); Ok(()) },
+    },
+    ConfigParameter {
+        name: "appenddirname",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "appendonlydir",
+        mutable: false,
+        get: |c| c.appenddirname.clone(),
+        set: |c, v| { c.appenddirname = v.to_string(); Ok(()) },
+    },
+    ConfigParameter {
+        name: "appendfsync",
+        alias: None,
+        kind: ConfigKind::Enum(&["always", "everysec", "no"]),
+        default: "everysec",
+        mutable: true,
+        get: |c| c.appendfsync.to_string(),
+        set: |c, v| {
+            c.appendfsync = v.parse::<FsyncPolicy>().map_err(|_| "argument must be 'always', 'everysec' or 'no'".to_string())?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        name: "aof-load-truncated",
+        alias: None,
+        kind: ConfigKind::Bool,
+        default: "yes",
+        mutable: true,
+        get: |c| format_bool(c.aof_load_truncated),
+        set: |c, v| { c.aof_load_truncated = parse_bool(v)?; Ok(()) },
+    },
+    ConfigParameter {
+        name: "auto-aof-rewrite-percentage",
+        alias: None,
+        kind: ConfigKind::Integer { min: 0, max: i64::MAX },
+        default: "100",
+        mutable: true,
+        get: |c| c.auto_aof_rewrite_percentage.to_string(),
+        set: |c, v| { c.auto_aof_rewrite_percentage = parse_validated(v); Ok(()) },
+    },
+    ConfigParameter {
+        name: "auto-aof-rewrite-min-size",
+        alias: None,
+        kind: ConfigKind::Memory,
+        default: "67108864",
+        mutable: true,
+        get: |c| c.auto_aof_rewrite_min_size.to_string(),
+        set: |c, v| { c.auto_aof_rewrite_min_size = parse_memory(v).unwrap_or_default(); Ok(()) },
+    },
+    ConfigParameter {
+        name: "requirepass",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "",
+        mutable: true,
+        get: |c| c.requirepass.clone().unwrap_or_default(),
+        set: |c, v| { c.requirepass = (!v.is_empty()).then(|| v.to_string()); Ok(()) },
+    },
+    ConfigParameter {
+        name: "maxmemory",
+        alias: None,
+        kind: ConfigKind::Memory,
+        default: "0",
+        mutable: true,
+        get: |c| c.maxmemory.to_string(),
+        set: |c, v| { c.maxmemory = parse_memory(v).unwrap_or_default(); Ok(()) },
+    },
+    ConfigParameter {
+        name: "slowlog-log-slower-than",
+        alias: None,
+        kind: ConfigKind::Integer { min: -1, max: i64::MAX },
+        default: "10000",
+        mutable: true,
+        get: |c| c.slowlog_log_slower_than.to_string(),
+        set: |c, v| { c.slowlog_log_slower_than = parse_validated(v); Ok(()) },
+    },
+    ConfigParameter {
+        name: "slowlog-max-len",
+        alias: None,
+        kind: ConfigKind::Integer { min: 0, max: i64::MAX },
+        default: "128",
+        mutable: true,
+        get: |c| c.slowlog_max_len.to_string(),
+        set: |c, v| { c.slowlog_max_len = parse_validated(v); Ok(()) },
+    },
+    ConfigParameter {
+        name: "aclfile",
+        alias: None,
+        kind: ConfigKind::String,
+        default: "",
+        mutable: false,
+        get: |c| c.aclfile.clone().unwrap_or_default(),
+        set: |c, v| { c.aclfile = (!v.is_empty()).then(|| v.to_string()); Ok(()) },
+    },
+    ConfigParameter {
+        name: "acllog-max-len",
+        alias: None,
+        kind: ConfigKind::Integer { min: 0, max: i64::MAX },
+        default: "128",
+        mutable: true,
+        get: |c| c.acllog_max_len.to_string(),
+        set: |c, v| { c.acllog_max_len = parse_validated(v); Ok(()) },
+    },
+];
+
+/// Looks a parameter up by its name or alias, case-insensitively.
+pub fn find_parameter(name: &str) -> Option<&'static ConfigParameter> {
+    PARAMETERS.iter().find(|parameter| {
+        parameter.name.eq_ignore_ascii_case(name) || parameter.alias.is_some_and(|alias| alias.eq_ignore_ascii_case(name))
+    })
+}
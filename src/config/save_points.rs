@@ -0,0 +1,29 @@
+use crate::errors::app_errors::AppError;
+
+/// Take a snapshot once at least `changes` writes happened and `seconds` elapsed since the last save.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SavePoint {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+/// Parses `"<seconds> <changes> ..."` pairs. An empty string disables snapshotting.
+pub fn parse_save_points(value: &str) -> Result<Vec<SavePoint>, AppError> {
+    let numbers = value
+        .split_whitespace()
+        .map(|n| n.parse::<u64>().map_err(|_| AppError::InvalidSavePoints))
+        .collect::<Result<Vec<u64>, AppError>>()?;
+    if !numbers.len().is_multiple_of(2) {
+        return Err(AppError::InvalidSavePoints);
+    }
+
+    Ok(numbers.chunks(2).map(|pair| SavePoint { seconds: pair[0], changes: pair[1] }).collect())
+}
+
+pub fn format_save_points(save_points: &[SavePoint]) -> String {
+    save_points
+        .iter()
+        .map(|point| format!("{} {}", point.seconds, point.changes))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -1,52 +1,226 @@
-#[derive(Debug)]
+use std::path::{Path, PathBuf};
+use crate::config::buffer_limits::OutputBufferLimits;
+use crate::config::config_file::{read_config_file, ConfigLine};
+use crate::config::registry::{find_parameter, ConfigKind};
+use crate::config::save_points::{format_save_points, parse_save_points, SavePoint};
+use crate::constants::DEFAULT_SAVE_POINTS;
+use crate::enums::fsync_policy::FsyncPolicy;
+use crate::errors::app_errors::AppError;
+use crate::logging::{LogFormat, LogLevel};
+
+#[derive(Debug, Clone)]
 pub struct ServerConfig {
     pub(crate) port: u16,
-    pub(crate) host: String,
+    /// Addresses to listen on. `*` stands for every IPv4 address, `::*` for every IPv6 one,
+    /// and a leading `-` makes an address optional.
+    pub(crate) bind: Vec<String>,
+    pub(crate) protected_mode: bool,
+    pub(crate) maxclients: u64,
+    /// Seconds a client may stay idle before it is disconnected, `0` never disconnecting it.
+    pub(crate) timeout: u64,
+    /// Seconds of silence before accepted TCP connections send keepalive probes, `0` turning them off.
+    pub(crate) tcp_keepalive: u64,
+    pub(crate) client_output_buffer_limits: OutputBufferLimits,
+    /// Bytes a client may have buffered without completing a command.
+    pub(crate) client_query_buffer_limit: u64,
+    /// Largest bulk string a client may send.
+    pub(crate) proto_max_bulk_len: u64,
+    pub(crate) loglevel: LogLevel,
+    /// Where the log is written, standard output when not set.
+    pub(crate) logfile: Option<String>,
+    pub(crate) log_format: LogFormat,
+    pub(crate) syslog_enabled: bool,
+    pub(crate) syslog_ident: String,
+    pub(crate) syslog_facility: String,
     pub(crate) master_port: u16,
     pub(crate) master_host: String,
     pub(crate) is_replication: bool,
+    pub(crate) dir: String,
+    pub(crate) dbfilename: String,
+    pub(crate) rdbcompression: bool,
+    pub(crate) appendonly: bool,
+    pub(crate) appendfilename: String,
+    pub(crate) appendfsync: FsyncPolicy,
+    pub(crate) aof_load_truncated: bool,
+    pub(crate) appenddirname: String,
+    pub(crate) auto_aof_rewrite_percentage: u64,
+    pub(crate) auto_aof_rewrite_min_size: u64,
+    pub(crate) save_points: Vec<SavePoint>,
+    pub(crate) requirepass: Option<String>,
+    pub(crate) maxmemory: u64,
+    pub(crate) slowlog_log_slower_than: i64,
+    pub(crate) slowlog_max_len: u64,
+    pub(crate) aclfile: Option<String>,
+    pub(crate) acllog_max_len: u64,
+    pub(crate) unixsocket: Option<String>,
+    /// Permission bits applied to the Unix socket, `0` keeping the ones from the umask.
+    pub(crate) unixsocketperm: u32,
+    pub(crate) config_file: Option<PathBuf>,
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         ServerConfig{
             port: 6379,
-            host: "127.0.0.1".to_string(),
+            bind: vec!["127.0.0.1".to_string(), "-::1".to_string()],
+            protected_mode: true,
+            maxclients: 10000,
+            timeout: 0,
+            tcp_keepalive: 300,
+            client_output_buffer_limits: OutputBufferLimits::default(),
+            client_query_buffer_limit: 1024 * 1024 * 1024,
+            proto_max_bulk_len: 512 * 1024 * 1024,
+            loglevel: LogLevel::Notice,
+            logfile: None,
+            log_format: LogFormat::Plain,
+            syslog_enabled: false,
+            syslog_ident: "redis".to_string(),
+            syslog_facility: "local0".to_string(),
             master_port: 6379,
             master_host: "".to_string(),
             is_replication: false,
+            dir: ".".to_string(),
+            dbfilename: "dump.rdb".to_string(),
+            rdbcompression: true,
+            appendonly: false,
+            appendfilename: "appendonly.aof".to_string(),
+            appendfsync: FsyncPolicy::EverySec,
+            aof_load_truncated: true,
+            appenddirname: "appendonlydir".to_string(),
+            auto_aof_rewrite_percentage: 100,
+            auto_aof_rewrite_min_size: 64 * 1024 * 1024,
+            save_points: parse_save_points(DEFAULT_SAVE_POINTS).unwrap_or_default(),
+            requirepass: None,
+            maxmemory: 0,
+            slowlog_log_slower_than: 10000,
+            slowlog_max_len: 128,
+            aclfile: None,
+            acllog_max_len: 128,
+            unixsocket: None,
+            unixsocketperm: 0,
+            config_file: None,
         }
     }
 }
 
-pub fn get_server_config(args: std::env::Args) -> ServerConfig {
+/// Builds the configuration from an optional `redis.conf` path followed by `--directive value`
+/// pairs. Command line directives are applied after the file, so they take precedence.
+pub fn get_server_config(args: impl Iterator<Item = String>) -> Result<ServerConfig, AppError> {
     let mut config = ServerConfig::default();
-    let mut args_iter = args.skip(1);
-
-    while let Some(arg) = args_iter.next() {
-        match arg.as_str() {
-            "--port" | "-p" => {
-                if let Some(port_str) = args_iter.next() {
-                    if let Ok(port) = port_str.parse::<u16>() {
-                        config.port = port;
-                    }
-                }
-            }
-            "--replicaof"  | "-r" => {
-                config.is_replication = true;
-                if let Some(replicaof) = args_iter.next() {
-                    let parts: Vec<&str> = replicaof.split(' ').collect();
-                    if parts.len() == 2 {
-                        config.master_host = parts[0].to_string();
-                        if let Ok(port) = parts[1].parse::<u16>() {
-                            config.master_port = port;
-                        }
-                    }
-                }
+    let args: Vec<String> = args.skip(1).collect();
+    let mut lines = Vec::new();
+    let mut index = 0;
+
+    if let Some(path) = args.first().filter(|arg| !arg.starts_with('-')) {
+        lines = read_config_file(Path::new(path))?;
+        config.config_file = Some(PathBuf::from(path));
+        index = 1;
+    }
+
+    while index < args.len() {
+        let name = match args[index].as_str() {
+            "-p" => "port".to_string(),
+            "-r" => "replicaof".to_string(),
+            arg => match arg.strip_prefix("--") {
+                Some(name) => name.to_string(),
+                None => return Err(AppError::ConfigFileError(
+                    "command line".to_string(), index + 1, arg.to_string(), "expected a --directive".to_string(),
+                )),
+            },
+        };
+        let start = index;
+        index += 1;
+
+        let mut line_args = vec![name];
+        while index < args.len() && !args[index].starts_with("--") {
+            line_args.push(args[index].clone());
+            index += 1;
+        }
+        lines.push(ConfigLine {
+            source: "command line".to_string(),
+            number: start + 1,
+            content: args[start..index].join(" "),
+            args: line_args,
+        });
+    }
+
+    let mut save_seen = false;
+    for line in &mut lines {
+        let name = line.args[0].to_lowercase();
+        // Like Redis, repeated save lines add up instead of replacing each other
+        if name == "save" {
+            if save_seen && !config.save_points.is_empty() {
+                line.args.insert(1, format_save_points(&config.save_points));
             }
-            _ => {}
+            save_seen = true;
         }
+        apply_directive(&mut config, &name, &line.args[1..]).map_err(|reason| line.error(&reason))?;
     }
 
-    config
-}
\ No newline at end of file
+    Ok(config)
+}
+
+fn apply_directive(config: &mut ServerConfig, name: &str, args: &[String]) -> Result<(), String> {
+    let parameter = find_parameter(name).ok_or_else(|| "Bad directive or wrong number of arguments".to_string())?;
+    let value = match (parameter.kind, args) {
+        (ConfigKind::List, [_, ..]) => args.join(" "),
+        (_, [value]) => value.clone(),
+        _ => return Err("wrong number of arguments".to_string()),
+    };
+    parameter.set(config, &value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn config_from(args: &[&str]) -> Result<ServerConfig, AppError> {
+        get_server_config(std::iter::once("redis-server").chain(args.iter().copied()).map(String::from))
+    }
+
+    fn error_of(result: Result<ServerConfig, AppError>) -> (String, usize, String) {
+        match result {
+            Err(AppError::ConfigFileError(source, line, _, reason)) => (source, line, reason),
+            other => panic!("expected a config error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn applies_command_line_directives() {
+        let config = config_from(&["--port", "7000", "--bind", "127.0.0.1", "::1", "--appendonly", "yes"]).unwrap();
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.bind, ["127.0.0.1", "::1"]);
+        assert!(config.appendonly);
+        assert_eq!(config_from(&["-p", "7001"]).unwrap().port, 7001);
+    }
+
+    #[test]
+    fn lets_the_command_line_override_the_file() {
+        let path = std::env::temp_dir().join(format!("server-config-test-{}.conf", std::process::id()));
+        fs::write(&path, "port 7000\ndbfilename \"from file.rdb\"\nsave 900 1\nsave 60 100\n").unwrap();
+        let config = config_from(&[path.to_str().unwrap(), "--port", "7002"]);
+        fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        assert_eq!(config.port, 7002);
+        assert_eq!(config.dbfilename, "from file.rdb");
+        assert_eq!(config.config_file.as_deref(), Some(path.as_path()));
+        // Repeated save lines add up, while an empty one would clear them
+        assert_eq!(format_save_points(&config.save_points), "900 1 60 100");
+        assert!(config_from(&["--save", ""]).unwrap().save_points.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_directives_and_bad_values() {
+        let (source, line, reason) = error_of(config_from(&["--port", "7000", "--no-such-option", "1"]));
+        assert_eq!((source.as_str(), line, reason.as_str()), ("command line", 3, "Bad directive or wrong number of arguments"));
+
+        let (_, line, _) = error_of(config_from(&["--port", "70000"]));
+        assert_eq!(line, 1);
+        let (_, _, reason) = error_of(config_from(&["--port", "1", "2"]));
+        assert_eq!(reason, "wrong number of arguments");
+        let (_, line, reason) = error_of(config_from(&["-x"]));
+        assert_eq!((line, reason.as_str()), (1, "expected a --directive"));
+    }
+}
@@ -1,2 +1,10 @@
-pub const DEFAULT_SNAPSHOT_PERIOD: u32 = 60;
-pub const DEFAULT_CHANGE_THRESHOLD: u32 = 1000;
+pub const REDIS_VERSION: &str = "7.2.0";
+pub const DEFAULT_SAVE_POINTS: &str = "3600 1 300 100 60 10000";
+/// Seconds to wait before retrying a snapshot after a failed one.
+pub const SNAPSHOT_RETRY_DELAY: u64 = 5;
+/// Slow log entries keep at most this many arguments...
+pub const SLOWLOG_ENTRY_MAX_ARGC: usize = 32;
+/// ...each truncated to this many bytes.
+pub const SLOWLOG_ENTRY_MAX_STRING: usize = 128;
+/// Lines a MONITOR client may fall behind before it starts missing commands.
+pub const MONITOR_BACKLOG: usize = 4096;
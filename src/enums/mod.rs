@@ -1,2 +1,4 @@
 pub mod role;
 pub mod sign;
+pub mod value;
+pub mod fsync_policy;
@@ -0,0 +1,17 @@
+use crate::types::sorted_set::SortedSet;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(String),
+    SortedSet(SortedSet),
+}
+
+impl Value {
+    /// Rough number of payload bytes held by the value, used for memory reporting.
+    pub fn estimated_size(&self) -> usize {
+        match self {
+            Value::String(s) => s.len(),
+            Value::SortedSet(set) => set.iter().map(|(member, _)| member.len() + 8).sum(),
+        }
+    }
+}
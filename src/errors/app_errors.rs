@@ -1,23 +1,140 @@
 use std::fmt;
 use std::io::Error;
+use crate::rdb;
 
 #[derive(Debug)]
 pub enum AppError {
     InvalidExpirationValue,
+    InvalidExpireTime(String),
     WrongNumberOfArgumentsError,
     InvalidPattern,
     FileError(Error),
     InvalidFileFormat,
+    WrongType,
+    SyntaxError,
+    InvalidFloat,
+    InvalidInteger,
+    TimeoutOutOfRange,
+    InvalidCoordinates(f64, f64),
+    UnsupportedUnit,
+    MemberNotFound,
+    IncompatibleOptions(&'static str),
+    UnsupportedRdbVersion(u32),
+    UnsupportedRdbType(u8),
+    UnsupportedRdbValue(String, u8),
+    ChecksumMismatch,
+    DirectoryError(String, Error),
+    UnknownSubcommand(String),
+    BackgroundSaveInProgress,
+    BackgroundTaskFailed(String),
+    UnknownCommand(String),
+    TruncatedAof(u64),
+    InvalidManifest(usize),
+    AofDisabled,
+    AofRewriteInProgress,
+    BgsaveBlockedByAofRewrite,
+    InvalidSavePoints,
+    InvalidConfigValue(String, String),
+    ImmutableConfig(String),
+    NoConfigFile,
+    NoSuchClient,
+    InvalidClientName,
+    UnknownClientType(String),
+    NoAuth,
+    WrongPass,
+    AuthNotConfigured,
+    HelloNoAuth,
+    UnsupportedProtocol,
+    InvalidProtocolVersion,
+    AclSetUser(String, String),
+    InvalidUsername,
+    NoPermCommand(String, String),
+    NoPermKey,
+    DefaultUserRemoval,
+    NoSuchUser(String),
+    UnknownAclCategory(String),
+    NoAclFile,
+    AclFileError(String, usize, String),
+    LogFileError(String, Error),
+    SyslogError(Error),
+    ListenError(String, Error),
+    NoListeners,
+    ProtectedMode,
+    MaxClients,
+    ShutdownFailed,
+    AofWriteFailed(String),
+    NoShutdownInProgress,
+    UnsupportedConfigParameter(String),
+    ConfigFileError(String, usize, String, String),
 }
 
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AppError::InvalidExpirationValue => write!(f, "ERR value is not an integer or out of range"),
+            AppError::InvalidExpireTime(command) => write!(f, "ERR invalid expire time in '{}' command", command),
             AppError::WrongNumberOfArgumentsError => write!(f, "ERR wrong number of arguments for command"),
             AppError::InvalidPattern => write!(f, "ERR invalid pattern"),
             AppError::FileError(e) => write!(f, "ERR file error: {}", e),
             AppError::InvalidFileFormat => write!(f, "ERR invalid file format"),
+            AppError::WrongType => write!(f, "WRONGTYPE Operation against a key holding the wrong kind of value"),
+            AppError::SyntaxError => write!(f, "ERR syntax error"),
+            AppError::InvalidFloat => write!(f, "ERR value is not a valid float"),
+            AppError::InvalidInteger => write!(f, "ERR value is not an integer or out of range"),
+            AppError::TimeoutOutOfRange => write!(f, "ERR timeout is out of range"),
+            AppError::InvalidCoordinates(lon, lat) => write!(f, "ERR invalid longitude,latitude pair {:.6},{:.6}", lon, lat),
+            AppError::UnsupportedUnit => write!(f, "ERR unsupported unit provided. please use M, KM, FT, MI"),
+            AppError::MemberNotFound => write!(f, "ERR could not decode requested zset member"),
+            AppError::IncompatibleOptions(reason) => write!(f, "ERR {}", reason),
+            AppError::UnsupportedRdbVersion(version) => write!(f, "ERR unsupported RDB version: {}", version),
+            AppError::UnsupportedRdbType(t) => write!(f, "ERR unsupported RDB object type: {}", t),
+            AppError::UnsupportedRdbValue(key, t) => write!(f, "ERR cannot load key '{}': unsupported RDB {} value (type {})", key, rdb::type_name(*t), t),
+            AppError::ChecksumMismatch => write!(f, "ERR RDB file checksum mismatch"),
+            AppError::DirectoryError(dir, e) => write!(f, "ERR can't use directory '{}': {}", dir, e),
+            AppError::UnknownSubcommand(sub) => write!(f, "ERR unknown subcommand '{}'", sub),
+            AppError::BackgroundSaveInProgress => write!(f, "ERR Background save already in progress"),
+            AppError::BackgroundTaskFailed(e) => write!(f, "ERR background task failed: {}", e),
+            AppError::UnknownCommand(c) => write!(f, "ERR unknown command '{}'", c),
+            AppError::InvalidManifest(line) => write!(f, "ERR invalid AOF manifest at line {}", line),
+            AppError::AofDisabled => write!(f, "ERR append only file is not enabled"),
+            AppError::AofRewriteInProgress => write!(f, "ERR Background append only file rewriting already in progress"),
+            AppError::BgsaveBlockedByAofRewrite => write!(f, "ERR An AOF log rewriting in progress: can't BGSAVE right now. Use BGSAVE SCHEDULE in order to schedule a BGSAVE whenever possible."),
+            AppError::InvalidSavePoints => write!(f, "ERR Invalid save parameters"),
+            AppError::InvalidConfigValue(parameter, reason) => write!(f, "ERR CONFIG SET failed (possibly related to argument '{}') - {}", parameter, reason),
+            AppError::ImmutableConfig(parameter) => write!(f, "ERR CONFIG SET failed (possibly related to argument '{}') - can't set immutable config", parameter),
+            AppError::NoConfigFile => write!(f, "ERR The server is running without a config file"),
+            AppError::NoSuchClient => write!(f, "ERR No such client"),
+            AppError::InvalidClientName => write!(f, "ERR Client names cannot contain spaces, newlines or special characters."),
+            AppError::UnknownClientType(client_type) => write!(f, "ERR Unknown client type '{}'", client_type),
+            AppError::NoAuth => write!(f, "NOAUTH Authentication required."),
+            AppError::WrongPass => write!(f, "WRONGPASS invalid username-password pair or user is disabled."),
+            AppError::AuthNotConfigured => write!(f, "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?"),
+            AppError::HelloNoAuth => write!(f, "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time"),
+            AppError::UnsupportedProtocol => write!(f, "NOPROTO unsupported protocol version"),
+            AppError::InvalidProtocolVersion => write!(f, "ERR Protocol version is not an integer or out of range"),
+            AppError::AclSetUser(rule, reason) => write!(f, "ERR Error in ACL SETUSER modifier '{}': {}", rule, reason),
+            AppError::InvalidUsername => write!(f, "ERR Usernames can't contain spaces or null characters"),
+            AppError::NoPermCommand(user, command) => write!(f, "NOPERM User {} has no permissions to run the '{}' command", user, command),
+            AppError::NoPermKey => write!(f, "NOPERM No permissions to access a key"),
+            AppError::DefaultUserRemoval => write!(f, "ERR The 'default' user cannot be removed"),
+            AppError::NoSuchUser(user) => write!(f, "ERR User '{}' not found", user),
+            AppError::UnknownAclCategory(category) => write!(f, "ERR Unknown category '{}'", category),
+            AppError::NoAclFile => write!(f, "ERR This Redis instance is not configured to use an ACL file. You may want to specify users via the ACL SETUSER command and then issue a CONFIG REWRITE (assuming you have a Redis configuration file set) in order to store users in the Redis configuration."),
+            AppError::AclFileError(source, line, reason) => write!(f, "ERR {}:{}: {}", source, line, reason),
+            AppError::ListenError(addr, e) => write!(f, "Could not create server listening socket {}: {}", addr, e),
+            AppError::NoListeners => write!(f, "Configured to not listen anywhere, exiting."),
+            AppError::ShutdownFailed => write!(f, "ERR Errors trying to SHUTDOWN. Check logs."),
+            AppError::AofWriteFailed(reason) => write!(f, "MISCONF Errors writing to the AOF file: {}", reason),
+            AppError::NoShutdownInProgress => write!(f, "ERR No shutdown in progress."),
+            AppError::MaxClients => write!(f, "ERR max number of clients reached"),
+            AppError::ProtectedMode => write!(f, "DENIED Redis is running in protected mode because protected mode is enabled and no password is set for the default user. In this mode connections are only accepted from the loopback interface. If you want to connect from external computers to Redis you may adopt one of the following solutions: 1) Just disable protected mode sending the command 'CONFIG SET protected-mode no' from the loopback interface by connecting to Redis from the same host the server is running, however MAKE SURE Redis is not publicly accessible from internet if you do so. Use CONFIG REWRITE to make this change permanent. 2) Alternatively you can just disable the protected mode by editing the Redis configuration file, and setting the protected mode option to 'no', and then restarting the server. 3) If you started the server manually just for testing, restart it with the '--protected-mode no' option. 4) Set up an authentication password for the default user. NOTE: You only need to do one of the above things in order for the server to start accepting connections from the outside."),
+            AppError::LogFileError(path, e) => write!(f, "ERR can't open the log file '{}': {}", path, e),
+            AppError::SyslogError(e) => write!(f, "ERR can't connect to syslog: {}", e),
+            AppError::UnsupportedConfigParameter(parameter) => write!(f, "ERR Unknown option or number of arguments for CONFIG SET - '{}'", parameter),
+            AppError::ConfigFileError(source, line, content, reason) => write!(
+                f, "*** FATAL CONFIG FILE ERROR ***\nReading {}, at line {}\n>>> '{}'\n{}", source, line, content, reason
+            ),
+            AppError::TruncatedAof(valid) => write!(f, "ERR AOF ends with an incomplete command after {} valid bytes, set aof-load-truncated to yes to load it", valid),
         }
     }
 }
@@ -26,6 +143,9 @@ impl std::error::Error for AppError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             AppError::FileError(e) => Some(e),
+            AppError::DirectoryError(_, e) => Some(e),
+            AppError::LogFileError(_, e) => Some(e),
+            AppError::SyslogError(e) => Some(e),
             _ => None,
         }
     }
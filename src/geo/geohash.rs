@@ -0,0 +1,92 @@
+// Geohash encoding compatible with Redis: 26 bits per coordinate interleaved into a
+// 52-bit integer that is stored as the sorted set score.
+
+pub const GEO_STEP: u32 = 26;
+pub const GEO_LAT_MIN: f64 = -85.05112878;
+pub const GEO_LAT_MAX: f64 = 85.05112878;
+pub const GEO_LONG_MIN: f64 = -180.0;
+pub const GEO_LONG_MAX: f64 = 180.0;
+
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+pub fn is_valid_coordinate(longitude: f64, latitude: f64) -> bool {
+    (GEO_LONG_MIN..=GEO_LONG_MAX).contains(&longitude) && (GEO_LAT_MIN..=GEO_LAT_MAX).contains(&latitude)
+}
+
+fn interleave(x: u32, y: u32) -> u64 {
+    let spread = |v: u32| {
+        let mut v = v as u64;
+        v = (v | (v << 16)) & 0x0000FFFF0000FFFF;
+        v = (v | (v << 8)) & 0x00FF00FF00FF00FF;
+        v = (v | (v << 4)) & 0x0F0F0F0F0F0F0F0F;
+        v = (v | (v << 2)) & 0x3333333333333333;
+        (v | (v << 1)) & 0x5555555555555555
+    };
+    spread(x) | (spread(y) << 1)
+}
+
+fn deinterleave(bits: u64) -> (u32, u32) {
+    let squash = |v: u64| {
+        let mut v = v & 0x5555555555555555;
+        v = (v | (v >> 1)) & 0x3333333333333333;
+        v = (v | (v >> 2)) & 0x0F0F0F0F0F0F0F0F;
+        v = (v | (v >> 4)) & 0x00FF00FF00FF00FF;
+        v = (v | (v >> 8)) & 0x0000FFFF0000FFFF;
+        ((v | (v >> 16)) & 0x00000000FFFFFFFF) as u32
+    };
+    (squash(bits), squash(bits >> 1))
+}
+
+fn encode_with_range(longitude: f64, latitude: f64, lat_min: f64, lat_max: f64) -> u64 {
+    let scale = (1u64 << GEO_STEP) as f64;
+    let lat_offset = ((latitude - lat_min) / (lat_max - lat_min) * scale) as u32;
+    let long_offset = ((longitude - GEO_LONG_MIN) / (GEO_LONG_MAX - GEO_LONG_MIN) * scale) as u32;
+    interleave(lat_offset, long_offset)
+}
+
+/// Encodes a coordinate pair into the 52-bit integer Redis uses as the member score.
+pub fn encode(longitude: f64, latitude: f64) -> u64 {
+    encode_with_range(longitude, latitude, GEO_LAT_MIN, GEO_LAT_MAX)
+}
+
+/// Decodes a score back into the centre of its geohash cell as (longitude, latitude).
+pub fn decode(bits: u64) -> (f64, f64) {
+    let (lat_offset, long_offset) = deinterleave(bits);
+    let scale = (1u64 << GEO_STEP) as f64;
+
+    let lat_lo = GEO_LAT_MIN + (lat_offset as f64 / scale) * (GEO_LAT_MAX - GEO_LAT_MIN);
+    let lat_hi = GEO_LAT_MIN + ((lat_offset as f64 + 1.0) / scale) * (GEO_LAT_MAX - GEO_LAT_MIN);
+    let long_lo = GEO_LONG_MIN + (long_offset as f64 / scale) * (GEO_LONG_MAX - GEO_LONG_MIN);
+    let long_hi = GEO_LONG_MIN + ((long_offset as f64 + 1.0) / scale) * (GEO_LONG_MAX - GEO_LONG_MIN);
+
+    let longitude = ((long_lo + long_hi) / 2.0).clamp(GEO_LONG_MIN, GEO_LONG_MAX);
+    let latitude = ((lat_lo + lat_hi) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX);
+    (longitude, latitude)
+}
+
+/// Returns the standard 11 character base32 geohash, which uses the full [-90, 90] latitude range.
+pub fn to_base32(longitude: f64, latitude: f64) -> String {
+    let bits = encode_with_range(longitude, latitude, -90.0, 90.0);
+    (0..11)
+        .map(|i| {
+            let index = if i == 10 { 0 } else { (bits >> (52 - (i + 1) * 5)) & 0x1f };
+            GEOHASH_ALPHABET[index as usize] as char
+        })
+        .collect()
+}
+
+/// Great-circle distance in meters using the haversine formula.
+pub fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let lat1r = lat1.to_radians();
+    let lat2r = lat2.to_radians();
+    let u = ((lat2r - lat1r) / 2.0).sin();
+    let v = ((lon2.to_radians() - lon1.to_radians()) / 2.0).sin();
+    let a = u * u + lat1r.cos() * lat2r.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+/// Distance along the meridian between two latitudes.
+pub fn latitude_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (lat2.to_radians() - lat1.to_radians()).abs()
+}
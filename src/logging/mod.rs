@@ -0,0 +1,252 @@
+pub mod syslog;
+
+use std::fmt::Arguments;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use crate::config::server_config::ServerConfig;
+use crate::errors::app_errors::AppError;
+use crate::logging::syslog::Syslog;
+use crate::storage::unix_time_millis;
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Message severities, from the most verbose. `Nothing` is only used as the configured level,
+/// silencing the log entirely.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    Debug,
+    Verbose,
+    Notice,
+    Warning,
+    Nothing,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Verbose => "verbose",
+            LogLevel::Notice => "notice",
+            LogLevel::Warning => "warning",
+            LogLevel::Nothing => "nothing",
+        }
+    }
+
+    /// The character marking the level in plain log lines.
+    fn mark(&self) -> char {
+        match self {
+            LogLevel::Debug => '.',
+            LogLevel::Verbose => '-',
+            LogLevel::Notice => '*',
+            LogLevel::Warning | LogLevel::Nothing => '#',
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LogLevel::Debug,
+            1 => LogLevel::Verbose,
+            2 => LogLevel::Notice,
+            3 => LogLevel::Warning,
+            _ => LogLevel::Nothing,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "verbose" => Ok(LogLevel::Verbose),
+            "notice" => Ok(LogLevel::Notice),
+            "warning" => Ok(LogLevel::Warning),
+            "nothing" => Ok(LogLevel::Nothing),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How lines written to the log file or standard output are laid out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// `pid:role 19 Oct 2026 10:15:02.123 * message`, as Redis writes them.
+    Plain,
+    /// One JSON object per line.
+    Json,
+}
+
+impl LogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFormat::Plain => "plain",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(LogFormat::Plain),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+struct Logger {
+    format: LogFormat,
+    /// The log file, standard output when not set.
+    file: Option<File>,
+    syslog: Option<Syslog>,
+    /// `M` for a master, `S` for a replica.
+    role: char,
+}
+
+/// Checked before taking the logger lock, so filtered out messages cost a single atomic load.
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Notice as u8);
+static LOGGER: Mutex<Logger> = Mutex::new(Logger { format: LogFormat::Plain, file: None, syslog: None, role: 'M' });
+
+/// Sets up logging from the startup configuration. Until then messages go to standard output.
+pub fn init(config: &ServerConfig) -> Result<(), AppError> {
+    let file = match &config.logfile {
+        Some(path) => Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| AppError::LogFileError(path.clone(), e))?,
+        ),
+        None => None,
+    };
+    let syslog = match config.syslog_enabled {
+        true => Some(Syslog::connect(&config.syslog_ident, &config.syslog_facility)?),
+        false => None,
+    };
+
+    let mut logger = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    logger.file = file;
+    logger.syslog = syslog;
+    logger.role = if config.is_replication { 'S' } else { 'M' };
+    drop(logger);
+    apply_config(config);
+    Ok(())
+}
+
+/// Picks up the settings that CONFIG SET can change.
+pub fn apply_config(config: &ServerConfig) {
+    LEVEL.store(config.loglevel as u8, Ordering::Relaxed);
+    LOGGER.lock().unwrap_or_else(|e| e.into_inner()).format = config.log_format;
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    level >= LogLevel::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+/// Writes a message if `level` passes the configured one. Used through the `log_*!` macros.
+pub fn log(level: LogLevel, args: Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    let message = args.to_string();
+    let now = unix_time_millis();
+    let mut logger = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+
+    let line = match logger.format {
+        LogFormat::Plain => format!("{}:{} {} {} {}\n", std::process::id(), logger.role, plain_time(now), level.mark(), message),
+        LogFormat::Json => format!(
+            "{{\"timestamp\":\"{}\",\"pid\":{},\"role\":\"{}\",\"level\":\"{}\",\"message\":\"{}\"}}\n",
+            iso_time(now),
+            std::process::id(),
+            logger.role,
+            level.as_str(),
+            json_escape(&message)
+        ),
+    };
+    // There is nowhere left to report a failing log write
+    let _ = match logger.file.as_mut() {
+        Some(file) => file.write_all(line.as_bytes()),
+        None => std::io::stdout().lock().write_all(line.as_bytes()),
+    };
+    if let Some(syslog) = &logger.syslog {
+        syslog.send(level, &message);
+    }
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits a Unix time in milliseconds into UTC `(year, month, day, hour, minute, second, millis)`.
+fn civil_time(millis: u64) -> (i64, u32, u32, u64, u64, u64, u64) {
+    let days = (millis / 86_400_000) as i64;
+    let millis_of_day = millis % 86_400_000;
+
+    // Days since 1970-01-01 to a proleptic Gregorian date, counting eras of 400 years from March 1st
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (
+        year,
+        month,
+        day,
+        millis_of_day / 3_600_000,
+        millis_of_day / 60_000 % 60,
+        millis_of_day / 1000 % 60,
+        millis_of_day % 1000,
+    )
+}
+
+/// `19 Oct 2026 10:15:02.123`, in UTC.
+fn plain_time(millis: u64) -> String {
+    let (year, month, day, hour, minute, second, millis) = civil_time(millis);
+    format!("{:02} {} {} {:02}:{:02}:{:02}.{:03}", day, MONTHS[month as usize - 1], year, hour, minute, second, millis)
+}
+
+/// `2026-10-19T10:15:02.123Z`.
+fn iso_time(millis: u64) -> String {
+    let (year, month, day, hour, minute, second, millis) = civil_time(millis);
+    format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, hour, minute, second, millis)
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => { $crate::logging::log($crate::logging::LogLevel::Debug, format_args!($($arg)*)) };
+}
+
+macro_rules! log_verbose {
+    ($($arg:tt)*) => { $crate::logging::log($crate::logging::LogLevel::Verbose, format_args!($($arg)*)) };
+}
+
+macro_rules! log_notice {
+    ($($arg:tt)*) => { $crate::logging::log($crate::logging::LogLevel::Notice, format_args!($($arg)*)) };
+}
+
+macro_rules! log_warning {
+    ($($arg:tt)*) => { $crate::logging::log($crate::logging::LogLevel::Warning, format_args!($($arg)*)) };
+}
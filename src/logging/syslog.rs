@@ -0,0 +1,48 @@
+use std::os::unix::net::UnixDatagram;
+use crate::errors::app_errors::AppError;
+use crate::logging::LogLevel;
+
+/// Where the local syslog daemon listens on Linux and on macOS.
+const SYSLOG_SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+
+pub const SYSLOG_FACILITIES: &[&str] =
+    &["user", "local0", "local1", "local2", "local3", "local4", "local5", "local6", "local7"];
+
+/// Sends log messages to the local syslog daemon.
+pub struct Syslog {
+    socket: UnixDatagram,
+    ident: String,
+    facility: u8,
+}
+
+impl Syslog {
+    pub fn connect(ident: &str, facility: &str) -> Result<Self, AppError> {
+        let facility = match facility.to_lowercase().as_str() {
+            "user" => 1,
+            local => local.strip_prefix("local").and_then(|n| n.parse::<u8>().ok()).filter(|n| *n <= 7).map_or(1, |n| 16 + n),
+        };
+        let socket = UnixDatagram::unbound().map_err(AppError::SyslogError)?;
+        let mut result = Ok(());
+        for path in SYSLOG_SOCKETS {
+            result = socket.connect(path);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result.map_err(AppError::SyslogError)?;
+        Ok(Syslog { socket, ident: ident.to_string(), facility })
+    }
+
+    /// Sends one message in the traditional `<priority>ident[pid]: message` format.
+    pub fn send(&self, level: LogLevel, message: &str) {
+        let severity = match level {
+            LogLevel::Debug => 7,
+            LogLevel::Verbose => 6,
+            LogLevel::Notice => 5,
+            LogLevel::Warning | LogLevel::Nothing => 4,
+        };
+        let line = format!("<{}>{}[{}]: {}", self.facility * 8 + severity, self.ident, std::process::id(), message);
+        // A syslog daemon that went away must not take the server down with it
+        let _ = self.socket.send(line.as_bytes());
+    }
+}
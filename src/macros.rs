@@ -3,19 +3,20 @@ macro_rules! init_snapshotting {
         let storage_clone = Arc::clone(&$storage);
         tokio::spawn(async move {
             let mut i = 0;
-            let mut time_to_sleep= DEFAULT_SNAPSHOT_PERIOD as u64;
             loop {
-                tokio::time::sleep(Duration::from_secs(time_to_sleep)).await;
-                let mut storage = storage_clone.lock().await;
-                if storage.should_take_snapshot() {
-                    println!("Executing snapshot: {}", i);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                let should_take_snapshot = {
+                    let storage = storage_clone.lock().await;
+                    !storage.background_job_in_progress() && storage.should_take_snapshot()
+                };
+
+                if should_take_snapshot {
+                    log_notice!("Executing snapshot: {}", i);
                     i += 1;
 
-                    if let Err(e) = storage.save_rdb_file() {
-                        println!("Error executing snapshot: {}", e);
+                    if let Err(e) = bgsave(&storage_clone, false).await {
+                        log_warning!("Error executing snapshot: {}", e);
                     }
-                } else {
-                    time_to_sleep = storage.snapshot.snapshot_period_secs as u64;
                 }
             }
         });
@@ -26,11 +27,94 @@ macro_rules! load_rdb_file {
     ($storage:expr) => {
         {
             let mut storage = $storage.lock().await;
-            println!("Init loading RDB File...");
+            log_notice!("Init loading RDB File from {}...", storage.dump_path().display());
             match storage.load_rdb_file() {
-                Ok(()) => println!("RDB File loaded successfully"),
-                Err(e) => println!("Error loading RDB File: {}", e)
+                Ok(summary) => log_notice!(
+                    "RDB File loaded successfully (version {}): {} keys loaded, {} expired, {} skipped",
+                    summary.version, summary.loaded_keys, summary.expired_keys, summary.skipped_keys
+                ),
+                Err(AppError::FileError(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                    log_notice!("No RDB File found, starting with an empty dataset")
+                }
+                Err(e) => log_warning!("Error loading RDB File: {}", e)
             }
         }
     };
-}
\ No newline at end of file
+}
+
+macro_rules! init_aof_fsync {
+    ($storage:expr) => {
+        let storage_clone = Arc::clone(&$storage);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                // Only the handle is taken under the lock, the fsync itself runs without blocking clients
+                let handle = {
+                    let mut storage = storage_clone.lock().await;
+                    let Some(aof) = storage.aof.as_mut() else {
+                        continue;
+                    };
+                    // Writes are refused until the commands a failed write left behind are on disk
+                    if !aof.last_write_ok {
+                        match aof.flush_pending() {
+                            Ok(()) => log_warning!("AOF write error looks solved, Redis can write again."),
+                            Err(e) => log_warning!("Error writing to AOF, retrying: {}", e),
+                        }
+                    }
+                    match aof.fsync {
+                        FsyncPolicy::EverySec => aof.sync_handle(),
+                        _ => continue,
+                    }
+                };
+
+                let result = match handle {
+                    Ok(file) => match tokio::task::spawn_blocking(move || file.sync_data()).await {
+                        Ok(result) => result.map_err(AppError::FileError),
+                        Err(e) => Err(AppError::BackgroundTaskFailed(e.to_string())),
+                    },
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    log_warning!("Error executing AOF fsync: {}", e);
+                }
+            }
+        });
+    };
+}
+
+
+macro_rules! init_aof_auto_rewrite {
+    ($storage:expr) => {
+        let storage_clone = Arc::clone(&$storage);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                let mut storage = storage_clone.lock().await;
+                let should_rewrite = !storage.snapshot.bgsave_in_progress
+                    && storage.aof.as_ref().is_some_and(|aof| aof.should_auto_rewrite());
+
+                if should_rewrite {
+                    log_notice!("Starting automatic rewrite of AOF");
+                    if let Err(e) = spawn_rewrite(&storage_clone, &mut storage) {
+                        log_warning!("Error starting automatic AOF rewrite: {}", e);
+                    }
+                }
+            }
+        });
+    };
+}
+
+macro_rules! init_clients_cron {
+    ($info_server:expr) => {
+        let info_server_clone = Arc::clone(&$info_server);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                let closed = info_server_clone.lock().await.clients.kill_idle();
+                if closed > 0 {
+                    log_verbose!("Closed {} idle client(s) after the configured timeout", closed);
+                }
+            }
+        });
+    };
+}
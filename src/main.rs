@@ -1,4 +1,6 @@
 extern crate core;
+#[macro_use]
+mod logging;
 mod storage;
 mod commands;
 mod resp;
@@ -9,52 +11,94 @@ mod enums;
 #[macro_use]
 mod macros;
 mod constants;
+mod types;
+mod geo;
+mod rdb;
+mod utils;
+mod persistence;
+mod acl;
 
-use crate::constants::DEFAULT_SNAPSHOT_PERIOD;
 use std::env::args;
-use tokio::net::TcpListener;
 use std::sync::Arc;
 use std::time::{Duration};
 use tokio::sync::Mutex;
-use crate::commands::handler::handle_connection;
 use crate::config::info_server::InfoServer;
 use crate::config::server_config::{get_server_config};
+use crate::errors::app_errors::AppError;
+use crate::enums::fsync_policy::FsyncPolicy;
+use crate::persistence::aof::init_aof;
+use crate::persistence::bgsave::bgsave;
+use crate::persistence::rewrite::spawn_rewrite;
+use crate::servers::listener::{accept_clients, bind_listeners};
 use crate::servers::replication::ServerReplication;
+use crate::servers::shutdown::handle_signals;
 use crate::storage::Storage;
 
 #[tokio::main]
 async fn main() {
-    let config = Arc::new(get_server_config(args()));
-    let listener = TcpListener::bind(format!("{}:{}", config.host, config.port)).await.unwrap();
-    let storage = Arc::new(Mutex::new(Storage::new()));
+    let config = match get_server_config(args()) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            log_warning!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = logging::init(&config) {
+        log_warning!("{}", e);
+        std::process::exit(1);
+    }
+    if config.maxmemory > 0 {
+        log_warning!("maxmemory is set but eviction is not supported yet, the limit is not enforced");
+    }
+    if let Some(path) = &config.config_file {
+        log_notice!("Configuration loaded from {}", path.display());
+    }
+    let listeners = match bind_listeners(&config).await {
+        Ok(listeners) => listeners,
+        Err(e) => {
+            log_warning!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let storage = Arc::new(Mutex::new(Storage::new(&config)));
     let info_server = Arc::new(Mutex::new(InfoServer::new(Arc::clone(&config))));
 
+    if config.aclfile.is_some() {
+        if let Err(e) = info_server.lock().await.acl.load() {
+            log_warning!("Error loading ACL file: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Err(e) = storage.lock().await.ensure_dir() {
+        log_warning!("Error preparing persistence directory: {}", e);
+    }
+
     if config.is_replication {
         let config_clone = Arc::clone(&config);
         let mut replication_server = ServerReplication::new(config_clone).await;
         replication_server.handshake().await;
+    } else if config.appendonly {
+        if let Err(e) = init_aof(&storage, &config).await {
+            log_warning!("Error loading AOF: {}", e);
+            std::process::exit(1);
+        }
     } else {
         load_rdb_file!(storage);
     }
 
     init_snapshotting!(storage);
+    init_aof_fsync!(storage);
+    init_aof_auto_rewrite!(storage);
+    init_clients_cron!(info_server);
 
-    loop {
-        let res = listener.accept().await;
-
-        match res {
-            Ok((stream, addr)) => {
-                println!("Accepted connection from {addr}");
-                let storage_clone = Arc::clone(&storage);
-                let info_server_clone = Arc::clone(&info_server);
-                tokio::spawn(async move {
-                    handle_connection(stream, storage_clone, info_server_clone).await.unwrap()
-                });
-            }
-            Err(e) => {
-                println!("Error: {}", e)
-            }
-        }
+    tokio::spawn(handle_signals(Arc::clone(&storage), Arc::clone(&info_server)));
+
+    let accept_loops: Vec<_> = listeners
+        .into_iter()
+        .map(|listener| tokio::spawn(accept_clients(listener, Arc::clone(&storage), Arc::clone(&info_server))))
+        .collect();
+    for accept_loop in accept_loops {
+        let _ = accept_loop.await;
     }
 }
-
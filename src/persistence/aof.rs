@@ -0,0 +1,384 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::commands::geo;
+use crate::config::server_config::ServerConfig;
+use crate::enums::fsync_policy::FsyncPolicy;
+use crate::errors::app_errors::AppError;
+use crate::persistence::manifest::{AofFile, AofFileType, AofManifest};
+use crate::rdb::reader::RdbReader;
+use crate::rdb::writer::save_rdb;
+use crate::resp::parser::extract_set_command_args;
+use crate::resp::parser::Parser::Array;
+use crate::storage::Storage;
+
+#[derive(Debug)]
+pub struct Aof {
+    file: File,
+    pub dir: PathBuf,
+    pub filename: String,
+    pub manifest: AofManifest,
+    pub fsync: FsyncPolicy,
+    pub last_write_ok: bool,
+    /// Why the last write or fsync failed, reported to writes refused until it succeeds again.
+    pub last_write_error: String,
+    /// Commands whose write failed, retried before anything else is appended.
+    pending: Vec<u8>,
+    /// Length of the current incremental file up to its last complete command.
+    written_len: u64,
+    pub current_size: u64,
+    pub base_size: u64,
+    pub rewrite_in_progress: bool,
+    pub rewrite_scheduled: bool,
+    pub last_rewrite_ok: bool,
+    pub auto_rewrite_percentage: u64,
+    pub auto_rewrite_min_size: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct AofSummary {
+    pub commands: usize,
+    pub truncated_bytes: u64,
+}
+
+impl Aof {
+    /// Opens the multi-part AOF described by `manifest`, appending to its last incremental file
+    /// or starting a new one.
+    pub fn open(dir: PathBuf, mut manifest: AofManifest, config: &ServerConfig) -> Result<Self, AppError> {
+        let manifest_path = dir.join(format!("{}.manifest", config.appendfilename));
+        if manifest.incrs.is_empty() {
+            let seq = manifest.next_incr_seq();
+            let name = format!("{}.{}.incr.aof", config.appendfilename, seq);
+            manifest.incrs.push(AofFile { name, seq, file_type: AofFileType::Incr });
+        }
+        manifest.save(&manifest_path)?;
+
+        let file_size = |name: &str| fs::metadata(dir.join(name)).map(|m| m.len()).unwrap_or(0);
+        let base_size = manifest.base.as_ref().map_or(0, |base| file_size(&base.name));
+        let current_size = base_size + manifest.incrs.iter().map(|incr| file_size(&incr.name)).sum::<u64>();
+        let incr = manifest.incrs.last().ok_or(AppError::InvalidManifest(0))?;
+        let written_len = file_size(&incr.name);
+
+        Ok(Aof {
+            file: open_append(&dir.join(&incr.name))?,
+            dir,
+            filename: config.appendfilename.clone(),
+            manifest,
+            fsync: config.appendfsync,
+            last_write_ok: true,
+            last_write_error: String::new(),
+            pending: Vec::new(),
+            written_len,
+            current_size,
+            base_size,
+            rewrite_in_progress: false,
+            rewrite_scheduled: false,
+            last_rewrite_ok: true,
+            auto_rewrite_percentage: config.auto_aof_rewrite_percentage,
+            auto_rewrite_min_size: config.auto_aof_rewrite_min_size,
+        })
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.manifest", self.filename))
+    }
+
+    /// Starts a new incremental file and records it in the manifest, so that subsequent writes land there.
+    pub fn open_incr(&mut self) -> Result<(), AppError> {
+        let seq = self.manifest.next_incr_seq();
+        let name = format!("{}.{}.incr.aof", self.filename, seq);
+        let file = open_append(&self.dir.join(&name))?;
+
+        let mut manifest = self.manifest.clone();
+        manifest.incrs.push(AofFile { name, seq, file_type: AofFileType::Incr });
+        manifest.save(&self.manifest_path())?;
+
+        self.manifest = manifest;
+        self.file = file;
+        // Commands still pending go to the new file, which replays them after the new base
+        self.written_len = 0;
+        Ok(())
+    }
+
+    /// Appends a command in RESP form. The write reaches the OS before the client gets its reply,
+    /// while fsync follows the configured policy.
+    pub fn append(&mut self, command: &str, args: &[String]) -> Result<(), AppError> {
+        let mut parts = Vec::with_capacity(args.len() + 1);
+        parts.push(command.to_string());
+        parts.extend_from_slice(args);
+        self.pending.extend_from_slice(Array(parts).serialize().as_bytes());
+        self.flush_pending()
+    }
+
+    /// Writes the commands left by failed writes and fsyncs them if the policy asks for it,
+    /// recording whether the log is healthy again.
+    pub fn flush_pending(&mut self) -> Result<(), AppError> {
+        let result = self.write_pending();
+        self.last_write_ok = result.is_ok();
+        if let Err(e) = &result {
+            self.last_write_error = e.to_string();
+        }
+        result
+    }
+
+    fn write_pending(&mut self) -> Result<(), AppError> {
+        if !self.pending.is_empty() {
+            if let Err(e) = self.file.write_all(&self.pending) {
+                // Cut off a partial write so the file never ends in the middle of a command
+                let _ = self.file.set_len(self.written_len);
+                return Err(AppError::FileError(e));
+            }
+            self.written_len += self.pending.len() as u64;
+            self.current_size += self.pending.len() as u64;
+            self.pending.clear();
+        }
+        match self.fsync {
+            FsyncPolicy::Always => self.sync(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fsyncs everything appended so far, regardless of the configured policy.
+    pub fn sync(&self) -> Result<(), AppError> {
+        self.file.sync_data().map_err(AppError::FileError)
+    }
+
+    /// Returns a handle that can be fsynced without holding the storage lock.
+    pub fn sync_handle(&self) -> Result<File, AppError> {
+        self.file.try_clone().map_err(AppError::FileError)
+    }
+
+    /// Whether the log grew enough since the last rewrite to trigger an automatic one.
+    pub fn should_auto_rewrite(&self) -> bool {
+        if self.rewrite_in_progress || self.auto_rewrite_percentage == 0 || self.current_size < self.auto_rewrite_min_size {
+            return false;
+        }
+        let base = self.base_size.max(1);
+        self.current_size.saturating_sub(base) * 100 / base >= self.auto_rewrite_percentage
+    }
+
+    /// Makes `base` the new base file, dropping the previous base and every incremental file
+    /// except the one that received writes during the rewrite.
+    pub fn finish_rewrite(&mut self, base: AofFile, base_size: u64) -> Result<(), AppError> {
+        let mut manifest = self.manifest.clone();
+        let current_incr = manifest.incrs.pop();
+        let obsolete: Vec<AofFile> = manifest.base.take().into_iter().chain(manifest.incrs.drain(..)).collect();
+
+        manifest.base = Some(base);
+        manifest.incrs.extend(current_incr);
+        manifest.save(&self.manifest_path())?;
+        self.manifest = manifest;
+
+        for file in obsolete {
+            if let Err(e) = fs::remove_file(self.dir.join(&file.name)) {
+                log_warning!("Error removing obsolete AOF file {}: {}", file.name, e);
+            }
+        }
+
+        let incr_size = self.manifest.incrs.last()
+            .and_then(|incr| fs::metadata(self.dir.join(&incr.name)).ok())
+            .map_or(0, |m| m.len());
+        self.base_size = base_size;
+        self.current_size = base_size + incr_size;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<File, AppError> {
+    OpenOptions::new().create(true).append(true).open(path).map_err(AppError::FileError)
+}
+
+/// Outcome of reading one command from the log.
+enum Frame {
+    Command(Vec<String>),
+    Eof,
+    Truncated,
+}
+
+fn read_line<R: BufRead>(reader: &mut R, consumed: &mut u64) -> Result<Option<String>, AppError> {
+    let mut line = Vec::new();
+    let read = reader.read_until(b'\n', &mut line).map_err(AppError::FileError)?;
+    *consumed += read as u64;
+    if !line.ends_with(b"\r\n") {
+        return Ok(None);
+    }
+    line.truncate(line.len() - 2);
+    String::from_utf8(line).map(Some).map_err(|_| AppError::InvalidFileFormat)
+}
+
+fn read_frame<R: BufRead>(reader: &mut R) -> Result<(Frame, u64), AppError> {
+    let mut consumed = 0;
+    if reader.fill_buf().map_err(AppError::FileError)?.is_empty() {
+        return Ok((Frame::Eof, 0));
+    }
+
+    let Some(header) = read_line(reader, &mut consumed)? else {
+        return Ok((Frame::Truncated, consumed));
+    };
+    let count = header.strip_prefix('*').and_then(|n| n.parse::<usize>().ok()).ok_or(AppError::InvalidFileFormat)?;
+
+    let mut parts = Vec::with_capacity(count);
+    for _ in 0..count {
+        let Some(len_line) = read_line(reader, &mut consumed)? else {
+            return Ok((Frame::Truncated, consumed));
+        };
+        let len = len_line.strip_prefix('$').and_then(|n| n.parse::<usize>().ok()).ok_or(AppError::InvalidFileFormat)?;
+
+        let mut data = vec![0; len + 2];
+        match reader.read_exact(&mut data) {
+            Ok(()) => consumed += data.len() as u64,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok((Frame::Truncated, consumed)),
+            Err(e) => return Err(AppError::FileError(e)),
+        }
+        if !data.ends_with(b"\r\n") {
+            return Err(AppError::InvalidFileFormat);
+        }
+        data.truncate(len);
+        parts.push(String::from_utf8_lossy(&data).into_owned());
+    }
+
+    Ok((Frame::Command(parts), consumed))
+}
+
+/// Applies a logged write command to the dataset.
+pub async fn apply_command(storage: &mut Storage, command: &str, args: Vec<String>) -> Result<(), AppError> {
+    match command {
+        "set" => {
+            let (key, value, expires) = extract_set_command_args(args).await?;
+            storage.set(key, value, expires);
+        }
+        "del" => {
+            storage.del(args.iter().map(|s| s.as_str()).collect());
+        }
+        "geoadd" => {
+            geo::geoadd(&args, storage)?;
+        }
+        "geosearchstore" => {
+            geo::geosearchstore(&args, storage)?;
+        }
+        _ => return Err(AppError::UnknownCommand(command.to_string())),
+    }
+    Ok(())
+}
+
+/// Replays an append only file, which may start with an RDB preamble, into `storage`.
+pub async fn load_aof(storage: &mut Storage, path: &Path, allow_truncated: bool) -> Result<AofSummary, AppError> {
+    let file = File::open(path).map_err(AppError::FileError)?;
+    let mut reader = BufReader::new(file);
+    let mut summary = AofSummary::default();
+    let mut valid_up_to = 0;
+
+    if reader.fill_buf().map_err(AppError::FileError)?.starts_with(b"REDIS") {
+        let mut counting = CountingReader { inner: &mut reader, count: 0 };
+        RdbReader::new(&mut counting).load(&mut storage.items)?;
+        valid_up_to = counting.count;
+    }
+
+    loop {
+        match read_frame(&mut reader)? {
+            (Frame::Eof, _) => break,
+            (Frame::Command(mut parts), consumed) => {
+                if parts.is_empty() {
+                    return Err(AppError::InvalidFileFormat);
+                }
+                let command = parts.remove(0).to_lowercase();
+                apply_command(storage, &command, parts).await?;
+                summary.commands += 1;
+                valid_up_to += consumed;
+            }
+            (Frame::Truncated, consumed) => {
+                if !allow_truncated {
+                    return Err(AppError::TruncatedAof(valid_up_to));
+                }
+                log_warning!("AOF {} ends with an incomplete command, truncating it to {} bytes", path.display(), valid_up_to);
+                let file = OpenOptions::new().write(true).open(path).map_err(AppError::FileError)?;
+                file.set_len(valid_up_to).map_err(AppError::FileError)?;
+                summary.truncated_bytes = consumed;
+                break;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+struct CountingReader<R: Read> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+/// Loads the dataset when append only mode is on and opens the log for appending.
+///
+/// An existing AOF takes precedence over the RDB file. A single-file AOF from before the
+/// multi-part layout becomes the base of a new manifest. Without any AOF, the RDB file is loaded
+/// and written out as the first base so nothing is lost on restart.
+pub async fn init_aof(storage: &Arc<Mutex<Storage>>, config: &ServerConfig) -> Result<(), AppError> {
+    let mut storage = storage.lock().await;
+    let dir = Path::new(&storage.config.dir).join(&config.appenddirname);
+    fs::create_dir_all(&dir).map_err(|e| AppError::DirectoryError(dir.display().to_string(), e))?;
+
+    let manifest_path = dir.join(format!("{}.manifest", config.appendfilename));
+    let legacy_path = Path::new(&storage.config.dir).join(&config.appendfilename);
+    let mut manifest = AofManifest::default();
+
+    if manifest_path.exists() {
+        manifest = AofManifest::load(&manifest_path)?;
+        if let Some(base) = &manifest.base {
+            load_aof_part(&mut storage, &dir.join(&base.name), false).await?;
+        }
+        let last = manifest.incrs.len().saturating_sub(1);
+        for (index, incr) in manifest.incrs.iter().enumerate() {
+            // Only the file that was being appended to when the server stopped may be cut short
+            load_aof_part(&mut storage, &dir.join(&incr.name), index == last && config.aof_load_truncated).await?;
+        }
+    } else if legacy_path.exists() {
+        // The file is only moved once it loaded, and the manifest pointing at it is written right
+        // away, so a failure never leaves the data where the next start would not look for it
+        load_aof_part(&mut storage, &legacy_path, config.aof_load_truncated).await?;
+        let name = format!("{}.1.base.aof", config.appendfilename);
+        log_notice!("Upgrading AOF {} to the multi-part layout", legacy_path.display());
+        fs::rename(&legacy_path, dir.join(&name)).map_err(AppError::FileError)?;
+        manifest.base = Some(AofFile { name, seq: 1, file_type: AofFileType::Base });
+        manifest.save(&manifest_path)?;
+    } else {
+        match storage.load_rdb_file() {
+            Ok(summary) => log_notice!("RDB File loaded successfully: {} keys loaded", summary.loaded_keys),
+            Err(AppError::FileError(e)) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let name = format!("{}.1.base.rdb", config.appendfilename);
+        save_rdb(&dir.join(&name), &storage.items, storage.config.rdbcompression)?;
+        manifest.base = Some(AofFile { name, seq: 1, file_type: AofFileType::Base });
+        log_notice!("Created AOF in {}", dir.display());
+    }
+
+    storage.snapshot.change_count = 0;
+    storage.aof = Some(Aof::open(dir, manifest, config)?);
+    Ok(())
+}
+
+async fn load_aof_part(storage: &mut Storage, path: &Path, allow_truncated: bool) -> Result<(), AppError> {
+    if path.extension().is_some_and(|ext| ext == "rdb") {
+        let file = File::open(path).map_err(AppError::FileError)?;
+        let summary = RdbReader::new(BufReader::new(file)).load(&mut storage.items)?;
+        log_notice!("AOF base {} loaded: {} keys", path.display(), summary.loaded_keys);
+    } else {
+        let summary = load_aof(storage, path, allow_truncated).await?;
+        log_notice!(
+            "AOF {} loaded: {} commands replayed, {} bytes truncated",
+            path.display(), summary.commands, summary.truncated_bytes
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{watch, Mutex};
+use crate::errors::app_errors::AppError;
+use crate::persistence::rewrite::spawn_rewrite;
+use crate::rdb::writer::save_rdb;
+use crate::storage::Storage;
+
+pub enum BgsaveStatus {
+    Started,
+    Scheduled,
+}
+
+/// Starts a background save, or schedules one to run after the current save when `schedule` is set.
+pub async fn bgsave(storage: &Arc<Mutex<Storage>>, schedule: bool) -> Result<BgsaveStatus, AppError> {
+    let mut guard = storage.lock().await;
+    let rewrite_in_progress = guard.aof.as_ref().is_some_and(|aof| aof.rewrite_in_progress);
+    if guard.snapshot.bgsave_in_progress || rewrite_in_progress {
+        if schedule {
+            guard.snapshot.bgsave_scheduled = true;
+            return Ok(BgsaveStatus::Scheduled);
+        }
+        return Err(match rewrite_in_progress {
+            true => AppError::BgsaveBlockedByAofRewrite,
+            false => AppError::BackgroundSaveInProgress,
+        });
+    }
+
+    spawn_save(storage, &mut guard)?;
+    Ok(BgsaveStatus::Started)
+}
+
+/// Captures a point-in-time view of the keyspace and serializes it on a blocking thread.
+pub fn spawn_save(storage: &Arc<Mutex<Storage>>, guard: &mut Storage) -> Result<(), AppError> {
+    guard.ensure_dir()?;
+    guard.snapshot.bgsave_in_progress = true;
+    guard.snapshot.last_save_attempt = Instant::now();
+    let (writing, writer) = watch::channel(());
+    guard.snapshot.bgsave_writer = Some(writer);
+
+    let items = guard.items.clone();
+    let path = guard.dump_path();
+    let compression = guard.config.rdbcompression;
+    let changes = guard.snapshot.change_count;
+    let storage = Arc::clone(storage);
+
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            let _writing = writing;
+            save_rdb(&path, &items, compression)
+        })
+            .await
+            .unwrap_or_else(|e| Err(AppError::BackgroundTaskFailed(e.to_string())));
+        match &result {
+            Ok(()) => log_notice!("Background saving terminated with success"),
+            Err(e) => log_warning!("Background saving error: {}", e),
+        }
+
+        let mut guard = storage.lock().await;
+        guard.record_save(&result, changes);
+        guard.snapshot.bgsave_in_progress = false;
+        guard.snapshot.bgsave_writer = None;
+
+        // A deferred AOF rewrite goes first, a scheduled save then waits for it to finish
+        if guard.aof.as_ref().is_some_and(|aof| aof.rewrite_scheduled) {
+            if let Err(e) = spawn_rewrite(&storage, &mut guard) {
+                log_warning!("Error starting scheduled AOF rewrite: {}", e);
+            }
+        } else if guard.snapshot.bgsave_scheduled {
+            guard.snapshot.bgsave_scheduled = false;
+            if let Err(e) = spawn_save(&storage, &mut guard) {
+                log_warning!("Error starting scheduled background save: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use std::fmt::Display;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use crate::errors::app_errors::AppError;
+use crate::utils::atomic_file::write_atomically;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AofFileType {
+    Base,
+    Incr,
+    History,
+}
+
+impl Display for AofFileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            AofFileType::Base => "b",
+            AofFileType::Incr => "i",
+            AofFileType::History => "h",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AofFile {
+    pub name: String,
+    pub seq: u64,
+    pub file_type: AofFileType,
+}
+
+/// Describes which files make up a multi-part AOF: one base snapshot followed by incremental logs.
+#[derive(Debug, Clone, Default)]
+pub struct AofManifest {
+    pub base: Option<AofFile>,
+    pub incrs: Vec<AofFile>,
+    pub history: Vec<AofFile>,
+}
+
+impl AofManifest {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let content = fs::read_to_string(path).map_err(AppError::FileError)?;
+        Self::parse(&content)
+    }
+
+    /// Parses manifest lines of `key value` pairs in any order. Errors name the offending line.
+    fn parse(content: &str) -> Result<Self, AppError> {
+        let mut manifest = AofManifest::default();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || AppError::InvalidManifest(index + 1);
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if !tokens.len().is_multiple_of(2) {
+                return Err(invalid());
+            }
+
+            let (mut name, mut seq, mut file_type) = (None, None, None);
+            for pair in tokens.chunks(2) {
+                match pair[0] {
+                    "file" => name = Some(pair[1].to_string()),
+                    "seq" => seq = Some(pair[1].parse::<u64>().map_err(|_| invalid())?),
+                    "type" => file_type = Some(match pair[1] {
+                        "b" => AofFileType::Base,
+                        "i" => AofFileType::Incr,
+                        "h" => AofFileType::History,
+                        _ => return Err(invalid()),
+                    }),
+                    // Unknown fields are ignored for forward compatibility
+                    _ => {}
+                }
+            }
+
+            let file = AofFile {
+                name: name.ok_or_else(invalid)?,
+                seq: seq.ok_or_else(invalid)?,
+                file_type: file_type.ok_or_else(invalid)?,
+            };
+            match file.file_type {
+                AofFileType::Base if manifest.base.is_some() => return Err(invalid()),
+                AofFileType::Base => manifest.base = Some(file),
+                AofFileType::Incr => manifest.incrs.push(file),
+                AofFileType::History => manifest.history.push(file),
+            }
+        }
+
+        manifest.incrs.sort_by_key(|file| file.seq);
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        write_atomically(path, |writer| {
+            for file in self.base.iter().chain(&self.history).chain(&self.incrs) {
+                writeln!(writer, "file {} seq {} type {}", file.name, file.seq, file.file_type).map_err(AppError::FileError)?;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn next_incr_seq(&self) -> u64 {
+        self.incrs.last().map_or(1, |file| file.seq + 1)
+    }
+
+    pub fn next_base_seq(&self) -> u64 {
+        self.base.as_ref().map_or(1, |file| file.seq + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(files: &[AofFile]) -> Vec<&str> {
+        files.iter().map(|file| file.name.as_str()).collect()
+    }
+
+    fn invalid_line(content: &str) -> Option<usize> {
+        match AofManifest::parse(content) {
+            Err(AppError::InvalidManifest(line)) => Some(line),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_base_history_and_sorted_incrs() {
+        let manifest = AofManifest::parse(
+            "file a.1.base.rdb seq 1 type b\n\
+             # comment\n\
+             \n\
+             file a.3.incr.aof seq 3 type i\n\
+             file a.0.base.rdb seq 0 type h\n\
+             file a.2.incr.aof seq 2 type i\n",
+        )
+        .unwrap();
+
+        let base = manifest.base.as_ref().unwrap();
+        assert_eq!((base.name.as_str(), base.seq, base.file_type), ("a.1.base.rdb", 1, AofFileType::Base));
+        assert_eq!(names(&manifest.incrs), ["a.2.incr.aof", "a.3.incr.aof"]);
+        assert_eq!(names(&manifest.history), ["a.0.base.rdb"]);
+        assert_eq!((manifest.next_incr_seq(), manifest.next_base_seq()), (4, 2));
+    }
+
+    #[test]
+    fn accepts_reordered_and_unknown_fields() {
+        let manifest = AofManifest::parse("type i seq 7 file a.7.incr.aof startoffset 120\n").unwrap();
+        assert_eq!(names(&manifest.incrs), ["a.7.incr.aof"]);
+        assert_eq!(manifest.incrs[0].seq, 7);
+        assert!(manifest.base.is_none());
+    }
+
+    #[test]
+    fn rejects_incomplete_lines() {
+        assert_eq!(invalid_line("file a.1.base.rdb seq 1\n"), Some(1));
+        assert_eq!(invalid_line("file a.1.base.rdb type b\n"), Some(1));
+        assert_eq!(invalid_line("seq 1 type b\n"), Some(1));
+        assert_eq!(invalid_line("file a.1.base.rdb seq 1 type\n"), Some(1));
+    }
+
+    #[test]
+    fn rejects_bad_values_and_a_second_base() {
+        assert_eq!(invalid_line("file a seq one type b\n"), Some(1));
+        assert_eq!(invalid_line("file a seq -1 type i\n"), Some(1));
+        assert_eq!(invalid_line("file a seq 1 type x\n"), Some(1));
+        assert_eq!(invalid_line("file a seq 1 type b\n\nfile b seq 2 type b\n"), Some(3));
+    }
+
+    #[test]
+    fn survives_a_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("manifest-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("appendonly.aof.manifest");
+
+        let file = |name: &str, seq, file_type| AofFile { name: name.to_string(), seq, file_type };
+        let manifest = AofManifest {
+            base: Some(file("a.2.base.rdb", 2, AofFileType::Base)),
+            incrs: vec![file("a.4.incr.aof", 4, AofFileType::Incr), file("a.5.incr.aof", 5, AofFileType::Incr)],
+            history: vec![file("a.1.base.rdb", 1, AofFileType::History)],
+        };
+        manifest.save(&path).unwrap();
+        let loaded = AofManifest::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.base.map(|base| base.name), Some("a.2.base.rdb".to_string()));
+        assert_eq!(names(&loaded.incrs), ["a.4.incr.aof", "a.5.incr.aof"]);
+        assert_eq!(names(&loaded.history), ["a.1.base.rdb"]);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod bgsave;
+pub mod aof;
+pub mod manifest;
+pub mod rewrite;
@@ -0,0 +1,79 @@
+use std::fs;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::errors::app_errors::AppError;
+use crate::persistence::bgsave::spawn_save;
+use crate::persistence::manifest::{AofFile, AofFileType};
+use crate::rdb::writer::save_rdb;
+use crate::storage::Storage;
+
+pub enum RewriteStatus {
+    Started,
+    Scheduled,
+}
+
+/// Starts an AOF rewrite, deferring it until the running background save finishes if there is one.
+pub async fn bgrewriteaof(storage: &Arc<Mutex<Storage>>) -> Result<RewriteStatus, AppError> {
+    let mut guard = storage.lock().await;
+    let bgsave_in_progress = guard.snapshot.bgsave_in_progress;
+    let aof = guard.aof.as_mut().ok_or(AppError::AofDisabled)?;
+
+    if aof.rewrite_in_progress {
+        return Err(AppError::AofRewriteInProgress);
+    }
+    if bgsave_in_progress {
+        aof.rewrite_scheduled = true;
+        return Ok(RewriteStatus::Scheduled);
+    }
+
+    spawn_rewrite(storage, &mut guard)?;
+    Ok(RewriteStatus::Started)
+}
+
+/// Compacts the AOF into a new base file built from a point-in-time view of the keyspace.
+///
+/// Writes issued while the base is being serialized go to a fresh incremental file opened here,
+/// which is the only incremental file kept once the rewrite completes.
+pub fn spawn_rewrite(storage: &Arc<Mutex<Storage>>, guard: &mut Storage) -> Result<(), AppError> {
+    let items = guard.items.clone();
+    let compression = guard.config.rdbcompression;
+    let aof = guard.aof.as_mut().ok_or(AppError::AofDisabled)?;
+
+    aof.open_incr()?;
+    aof.rewrite_in_progress = true;
+    aof.rewrite_scheduled = false;
+
+    let seq = aof.manifest.next_base_seq();
+    let base = AofFile { name: format!("{}.{}.base.rdb", aof.filename, seq), seq, file_type: AofFileType::Base };
+    let path = aof.dir.join(&base.name);
+    let storage = Arc::clone(storage);
+
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            save_rdb(&path, &items, compression)?;
+            fs::metadata(&path).map(|m| m.len()).map_err(AppError::FileError)
+        })
+        .await
+        .unwrap_or_else(|e| Err(AppError::BackgroundTaskFailed(e.to_string())));
+
+        let mut guard = storage.lock().await;
+        if let Some(aof) = guard.aof.as_mut() {
+            let result = result.and_then(|size| aof.finish_rewrite(base, size));
+            match &result {
+                Ok(()) => log_notice!("Background AOF rewrite finished successfully"),
+                Err(e) => log_warning!("Background AOF rewrite error: {}", e),
+            }
+            aof.rewrite_in_progress = false;
+            aof.last_rewrite_ok = result.is_ok();
+        }
+
+        if guard.snapshot.bgsave_scheduled {
+            guard.snapshot.bgsave_scheduled = false;
+            if let Err(e) = spawn_save(&storage, &mut guard) {
+                log_warning!("Error starting scheduled background save: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
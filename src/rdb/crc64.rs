@@ -0,0 +1,43 @@
+// CRC-64/Jones as used by Redis for RDB checksums (reflected, polynomial 0xad93d23594c935a9).
+
+const POLY: u64 = 0x95ac9329ac4bc9b5;
+
+const fn build_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static TABLE: [u64; 256] = build_table();
+
+pub fn update(mut crc: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        crc = TABLE[((crc ^ *byte as u64) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_jones_check_value() {
+        assert_eq!(update(0, b"123456789"), 0xe9c6d914c4b8d9ca);
+    }
+
+    #[test]
+    fn updates_incrementally() {
+        assert_eq!(update(update(0, b"1234"), b"56789"), update(0, b"123456789"));
+    }
+}
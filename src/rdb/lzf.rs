@@ -0,0 +1,154 @@
+// LZF decompression as used by Redis for compressed RDB strings.
+
+pub fn decompress(input: &[u8], expected_len: usize) -> Option<Vec<u8>> {
+    // A back reference expands at most three input bytes into 264, which bounds a plausible length
+    let mut output = Vec::with_capacity(expected_len.min(input.len().saturating_mul(88)));
+    let mut i = 0;
+
+    while i < input.len() {
+        let ctrl = input[i] as usize;
+        i += 1;
+
+        if ctrl < 32 {
+            // Literal run of ctrl + 1 bytes
+            let run = ctrl + 1;
+            output.extend_from_slice(input.get(i..i + run)?);
+            i += run;
+        } else {
+            // Back reference
+            let mut len = ctrl >> 5;
+            if len == 7 {
+                len += *input.get(i)? as usize;
+                i += 1;
+            }
+            let offset = ((ctrl & 0x1f) << 8) + *input.get(i)? as usize + 1;
+            i += 1;
+
+            let start = output.len().checked_sub(offset)?;
+            for j in 0..len + 2 {
+                output.push(output[start + j]);
+            }
+        }
+    }
+
+    (output.len() == expected_len).then_some(output)
+}
+
+const HASH_LOG: usize = 14;
+const MAX_LITERAL: usize = 32;
+const MAX_OFFSET: usize = 1 << 13;
+const MAX_MATCH: usize = 264;
+
+fn hash(bytes: &[u8]) -> usize {
+    let v = ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32;
+    (v.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+/// Compresses `input`, returning `None` when the result would not be smaller than `max_len`.
+pub fn compress(input: &[u8], max_len: usize) -> Option<Vec<u8>> {
+    let mut table = vec![usize::MAX; 1 << HASH_LOG];
+    let mut output = Vec::with_capacity(max_len);
+    let mut literal_pos = 0;
+    let mut literals = 0;
+    let mut ip = 0;
+    output.push(0);
+
+    while ip < input.len() {
+        if ip + 2 < input.len() {
+            let h = hash(&input[ip..]);
+            let reference = table[h];
+            table[h] = ip;
+
+            if reference != usize::MAX && ip - reference <= MAX_OFFSET && input[reference..reference + 3] == input[ip..ip + 3] {
+                let max_match = MAX_MATCH.min(input.len() - ip);
+                let mut len = 3;
+                while len < max_match && input[reference + len] == input[ip + len] {
+                    len += 1;
+                }
+
+                // Close the pending literal run before emitting the back reference
+                if literals == 0 {
+                    output.pop();
+                } else {
+                    output[literal_pos] = (literals - 1) as u8;
+                }
+
+                let offset = ip - reference - 1;
+                let encoded_len = len - 2;
+                if encoded_len < 7 {
+                    output.push(((encoded_len << 5) | (offset >> 8)) as u8);
+                } else {
+                    output.push(((7 << 5) | (offset >> 8)) as u8);
+                    output.push((encoded_len - 7) as u8);
+                }
+                output.push((offset & 0xff) as u8);
+
+                ip += len;
+                literal_pos = output.len();
+                literals = 0;
+                output.push(0);
+
+                if output.len() >= max_len {
+                    return None;
+                }
+                continue;
+            }
+        }
+
+        output.push(input[ip]);
+        literals += 1;
+        ip += 1;
+        if literals == MAX_LITERAL {
+            output[literal_pos] = (MAX_LITERAL - 1) as u8;
+            literal_pos = output.len();
+            literals = 0;
+            output.push(0);
+        }
+
+        if output.len() >= max_len {
+            return None;
+        }
+    }
+
+    if literals == 0 {
+        output.pop();
+    } else {
+        output[literal_pos] = (literals - 1) as u8;
+    }
+
+    (output.len() < max_len).then_some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_compressible_input() {
+        let input = "abcabcabcabc-hello-hello-hello-".repeat(40).into_bytes();
+        let compressed = compress(&input, input.len()).expect("repetitive input compresses");
+        assert!(compressed.len() < input.len());
+        assert_eq!(decompress(&compressed, input.len()), Some(input));
+    }
+
+    #[test]
+    fn round_trips_long_literal_runs() {
+        let input: Vec<u8> = (0..=255u8).chain(0..=255u8).collect();
+        let compressed = compress(&input, input.len()).expect("second half is a back reference");
+        assert_eq!(decompress(&compressed, input.len()), Some(input));
+    }
+
+    #[test]
+    fn refuses_incompressible_input() {
+        let input: Vec<u8> = (0..64u8).collect();
+        assert_eq!(compress(&input, input.len()), None);
+    }
+
+    #[test]
+    fn rejects_a_wrong_expected_length() {
+        let input = b"aaaaaaaaaaaaaaaaaaaaaaaa".to_vec();
+        let compressed = compress(&input, input.len()).unwrap();
+        assert_eq!(decompress(&compressed, input.len() + 1), None);
+        assert_eq!(decompress(&[0x05, b'a'], 6), None);
+    }
+}
@@ -0,0 +1,75 @@
+pub mod crc64;
+pub mod lzf;
+pub mod reader;
+pub mod writer;
+
+pub const RDB_VERSION: u32 = 11;
+pub const RDB_MAX_VERSION: u32 = 12;
+
+pub const OPCODE_SLOT_INFO: u8 = 0xF4;
+pub const OPCODE_FUNCTION2: u8 = 0xF5;
+pub const OPCODE_FUNCTION_PRE_GA: u8 = 0xF6;
+pub const OPCODE_MODULE_AUX: u8 = 0xF7;
+pub const OPCODE_IDLE: u8 = 0xF8;
+pub const OPCODE_FREQ: u8 = 0xF9;
+pub const OPCODE_AUX: u8 = 0xFA;
+pub const OPCODE_RESIZEDB: u8 = 0xFB;
+pub const OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+pub const OPCODE_EXPIRETIME: u8 = 0xFD;
+pub const OPCODE_SELECTDB: u8 = 0xFE;
+pub const OPCODE_EOF: u8 = 0xFF;
+
+pub const TYPE_STRING: u8 = 0;
+pub const TYPE_LIST: u8 = 1;
+pub const TYPE_SET: u8 = 2;
+pub const TYPE_ZSET: u8 = 3;
+pub const TYPE_HASH: u8 = 4;
+pub const TYPE_ZSET_2: u8 = 5;
+pub const TYPE_MODULE_PRE_GA: u8 = 6;
+pub const TYPE_MODULE_2: u8 = 7;
+pub const TYPE_HASH_ZIPMAP: u8 = 9;
+pub const TYPE_LIST_ZIPLIST: u8 = 10;
+pub const TYPE_SET_INTSET: u8 = 11;
+pub const TYPE_ZSET_ZIPLIST: u8 = 12;
+pub const TYPE_HASH_ZIPLIST: u8 = 13;
+pub const TYPE_LIST_QUICKLIST: u8 = 14;
+pub const TYPE_STREAM_LISTPACKS: u8 = 15;
+pub const TYPE_HASH_LISTPACK: u8 = 16;
+pub const TYPE_ZSET_LISTPACK: u8 = 17;
+pub const TYPE_LIST_QUICKLIST_2: u8 = 18;
+pub const TYPE_STREAM_LISTPACKS_2: u8 = 19;
+pub const TYPE_SET_LISTPACK: u8 = 20;
+pub const TYPE_STREAM_LISTPACKS_3: u8 = 21;
+pub const TYPE_HASH_METADATA_PRE_GA: u8 = 22;
+pub const TYPE_HASH_LISTPACK_EX_PRE_GA: u8 = 23;
+pub const TYPE_HASH_METADATA: u8 = 24;
+pub const TYPE_HASH_LISTPACK_EX: u8 = 25;
+
+pub const MODULE_OPCODE_EOF: u64 = 0;
+pub const MODULE_OPCODE_SINT: u64 = 1;
+pub const MODULE_OPCODE_UINT: u64 = 2;
+pub const MODULE_OPCODE_FLOAT: u64 = 3;
+pub const MODULE_OPCODE_DOUBLE: u64 = 4;
+pub const MODULE_OPCODE_STRING: u64 = 5;
+
+pub const ENC_INT8: u8 = 0;
+pub const ENC_INT16: u8 = 1;
+pub const ENC_INT32: u8 = 2;
+pub const ENC_LZF: u8 = 3;
+
+/// Human readable name of an RDB value type, used when reporting values that cannot be loaded.
+pub fn type_name(value_type: u8) -> &'static str {
+    match value_type {
+        TYPE_STRING => "string",
+        TYPE_LIST | TYPE_LIST_ZIPLIST | TYPE_LIST_QUICKLIST | TYPE_LIST_QUICKLIST_2 => "list",
+        TYPE_SET | TYPE_SET_INTSET | TYPE_SET_LISTPACK => "set",
+        TYPE_ZSET | TYPE_ZSET_2 | TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => "zset",
+        TYPE_HASH | TYPE_HASH_ZIPMAP | TYPE_HASH_ZIPLIST | TYPE_HASH_LISTPACK => "hash",
+        TYPE_HASH_METADATA_PRE_GA | TYPE_HASH_LISTPACK_EX_PRE_GA | TYPE_HASH_METADATA | TYPE_HASH_LISTPACK_EX => {
+            "hash with field expiration"
+        }
+        TYPE_MODULE_PRE_GA | TYPE_MODULE_2 => "module",
+        TYPE_STREAM_LISTPACKS | TYPE_STREAM_LISTPACKS_2 | TYPE_STREAM_LISTPACKS_3 => "stream",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use crate::enums::value::Value;
+use crate::errors::app_errors::AppError;
+use crate::rdb::*;
+use crate::storage::Item;
+use crate::types::keyspace::Keyspace;
+use crate::types::sorted_set::SortedSet;
+
+/// Largest buffer allocated up front for a string whose length is read from the file.
+const MAX_PREALLOC: usize = 64 * 1024;
+
+/// Length prefix of an RDB string or collection, which may instead flag a special string encoding.
+enum Length {
+    Plain(u64),
+    Encoded(u8),
+}
+
+#[derive(Debug, Default)]
+pub struct RdbSummary {
+    pub version: u32,
+    pub aux: HashMap<String, String>,
+    pub loaded_keys: usize,
+    pub expired_keys: usize,
+    pub skipped_keys: usize,
+}
+
+pub struct RdbReader<R: Read> {
+    reader: R,
+    crc: u64,
+}
+
+impl<R: Read> RdbReader<R> {
+    pub fn new(reader: R) -> Self {
+        RdbReader { reader, crc: 0 }
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), AppError> {
+        self.reader.read_exact(buf).map_err(AppError::FileError)?;
+        self.crc = crc64::update(self.crc, buf);
+        Ok(())
+    }
+
+    /// Reads `len` bytes, growing the buffer as data arrives so a corrupt length cannot force a huge allocation.
+    fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, AppError> {
+        let mut buf = Vec::with_capacity(len.min(MAX_PREALLOC));
+        (&mut self.reader).take(len as u64).read_to_end(&mut buf).map_err(AppError::FileError)?;
+        if buf.len() != len {
+            return Err(AppError::FileError(ErrorKind::UnexpectedEof.into()));
+        }
+        self.crc = crc64::update(self.crc, &buf);
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, AppError> {
+        let mut buf = [0; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_length_with_encoding(&mut self) -> Result<Length, AppError> {
+        let first = self.read_u8()?;
+        match first >> 6 {
+            0 => Ok(Length::Plain((first & 0x3f) as u64)),
+            1 => {
+                let second = self.read_u8()?;
+                Ok(Length::Plain((((first & 0x3f) as u64) << 8) | second as u64))
+            }
+            2 => match first {
+                0x80 => {
+                    let mut buf = [0; 4];
+                    self.read_exact(&mut buf)?;
+                    Ok(Length::Plain(u32::from_be_bytes(buf) as u64))
+                }
+                0x81 => {
+                    let mut buf = [0; 8];
+                    self.read_exact(&mut buf)?;
+                    Ok(Length::Plain(u64::from_be_bytes(buf)))
+                }
+                _ => Err(AppError::InvalidFileFormat),
+            },
+            _ => Ok(Length::Encoded(first & 0x3f)),
+        }
+    }
+
+    fn read_length(&mut self) -> Result<u64, AppError> {
+        match self.read_length_with_encoding()? {
+            Length::Plain(len) => Ok(len),
+            Length::Encoded(_) => Err(AppError::InvalidFileFormat),
+        }
+    }
+
+    fn read_raw_string(&mut self) -> Result<Vec<u8>, AppError> {
+        match self.read_length_with_encoding()? {
+            Length::Plain(len) => self.read_bytes(len as usize),
+            Length::Encoded(ENC_INT8) => Ok((self.read_u8()? as i8).to_string().into_bytes()),
+            Length::Encoded(ENC_INT16) => {
+                let mut buf = [0; 2];
+                self.read_exact(&mut buf)?;
+                Ok(i16::from_le_bytes(buf).to_string().into_bytes())
+            }
+            Length::Encoded(ENC_INT32) => {
+                let mut buf = [0; 4];
+                self.read_exact(&mut buf)?;
+                Ok(i32::from_le_bytes(buf).to_string().into_bytes())
+            }
+            Length::Encoded(ENC_LZF) => {
+                let compressed_len = self.read_length()? as usize;
+                let len = self.read_length()? as usize;
+                let compressed = self.read_bytes(compressed_len)?;
+                lzf::decompress(&compressed, len).ok_or(AppError::InvalidFileFormat)
+            }
+            Length::Encoded(_) => Err(AppError::InvalidFileFormat),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, AppError> {
+        let bytes = self.read_raw_string()?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Reads a score in the legacy ZSET format: a length byte followed by its ASCII representation.
+    fn read_string_double(&mut self) -> Result<f64, AppError> {
+        match self.read_u8()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => {
+                let bytes = self.read_bytes(len as usize)?;
+                parse_score(&bytes)
+            }
+        }
+    }
+
+    fn read_binary_double(&mut self) -> Result<f64, AppError> {
+        let mut buf = [0; 8];
+        self.read_exact(&mut buf)?;
+        Ok(f64::from_le_bytes(buf))
+    }
+
+    fn skip_strings(&mut self, count: u64) -> Result<(), AppError> {
+        for _ in 0..count {
+            self.read_raw_string()?;
+        }
+        Ok(())
+    }
+
+    /// Skips data serialized by a module, which describes each field with an opcode up to an EOF marker.
+    fn skip_module_fields(&mut self) -> Result<(), AppError> {
+        loop {
+            match self.read_length()? {
+                MODULE_OPCODE_EOF => return Ok(()),
+                MODULE_OPCODE_SINT | MODULE_OPCODE_UINT => {
+                    self.read_length()?;
+                }
+                MODULE_OPCODE_FLOAT => {
+                    self.read_exact(&mut [0; 4])?;
+                }
+                MODULE_OPCODE_DOUBLE => {
+                    self.read_exact(&mut [0; 8])?;
+                }
+                MODULE_OPCODE_STRING => {
+                    self.read_raw_string()?;
+                }
+                _ => return Err(AppError::InvalidFileFormat),
+            }
+        }
+    }
+
+    /// Reads the value stored under `key`. Types this server cannot represent are consumed and `None` is returned,
+    /// while types whose encoding cannot be skipped fail the load with an error naming the key.
+    fn read_value(&mut self, key: &str, value_type: u8) -> Result<Option<Value>, AppError> {
+        match value_type {
+            TYPE_STRING => Ok(Some(Value::String(self.read_string()?))),
+            TYPE_ZSET | TYPE_ZSET_2 => {
+                let len = self.read_length()?;
+                let mut set = SortedSet::new();
+                for _ in 0..len {
+                    let member = self.read_string()?;
+                    let score = match value_type {
+                        TYPE_ZSET => self.read_string_double()?,
+                        _ => self.read_binary_double()?,
+                    };
+                    set.insert(member, score);
+                }
+                Ok(Some(Value::SortedSet(set)))
+            }
+            TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => {
+                let blob = self.read_raw_string()?;
+                let entries = match value_type {
+                    TYPE_ZSET_ZIPLIST => parse_ziplist(&blob)?,
+                    _ => parse_listpack(&blob)?,
+                };
+                if entries.len() % 2 != 0 {
+                    return Err(AppError::InvalidFileFormat);
+                }
+
+                let mut set = SortedSet::new();
+                for pair in entries.chunks(2) {
+                    set.insert(String::from_utf8_lossy(&pair[0]).into_owned(), parse_score(&pair[1])?);
+                }
+                Ok(Some(Value::SortedSet(set)))
+            }
+            TYPE_LIST | TYPE_SET | TYPE_LIST_QUICKLIST => {
+                let len = self.read_length()?;
+                self.skip_strings(len)?;
+                Ok(None)
+            }
+            TYPE_HASH => {
+                let len = self.read_length()?;
+                self.skip_strings(len * 2)?;
+                Ok(None)
+            }
+            TYPE_LIST_QUICKLIST_2 => {
+                let len = self.read_length()?;
+                for _ in 0..len {
+                    self.read_length()?;
+                    self.read_raw_string()?;
+                }
+                Ok(None)
+            }
+            TYPE_HASH_ZIPMAP | TYPE_LIST_ZIPLIST | TYPE_SET_INTSET | TYPE_HASH_ZIPLIST | TYPE_HASH_LISTPACK
+            | TYPE_SET_LISTPACK => {
+                self.read_raw_string()?;
+                Ok(None)
+            }
+            TYPE_HASH_METADATA => {
+                // Minimum field expiration, then each field as its TTL, name and value
+                self.read_exact(&mut [0; 8])?;
+                let len = self.read_length()?;
+                for _ in 0..len {
+                    self.read_length()?;
+                    self.skip_strings(2)?;
+                }
+                Ok(None)
+            }
+            TYPE_HASH_LISTPACK_EX => {
+                self.read_exact(&mut [0; 8])?;
+                self.read_raw_string()?;
+                Ok(None)
+            }
+            TYPE_MODULE_2 => {
+                self.read_length()?;
+                self.skip_module_fields()?;
+                Ok(None)
+            }
+            _ => Err(AppError::UnsupportedRdbValue(key.to_string(), value_type)),
+        }
+    }
+
+    /// Reads a whole RDB stream into `items`, verifying the trailing checksum when one is present.
+    pub fn load(mut self, items: &mut Keyspace) -> Result<RdbSummary, AppError> {
+        let mut summary = RdbSummary::default();
+
+        let mut header = [0; 9];
+        self.read_exact(&mut header)?;
+        if &header[..5] != b"REDIS" {
+            return Err(AppError::InvalidFileFormat);
+        }
+        summary.version = std::str::from_utf8(&header[5..])
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or(AppError::InvalidFileFormat)?;
+        if summary.version == 0 || summary.version > RDB_MAX_VERSION {
+            return Err(AppError::UnsupportedRdbVersion(summary.version));
+        }
+
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64;
+        let mut db = 0;
+        let mut expire_at_ms: Option<i64> = None;
+
+        loop {
+            let opcode = self.read_u8()?;
+            match opcode {
+                OPCODE_EOF => break,
+                OPCODE_AUX => {
+                    let key = self.read_string()?;
+                    let value = self.read_string()?;
+                    summary.aux.insert(key, value);
+                }
+                OPCODE_RESIZEDB => {
+                    self.read_length()?;
+                    self.read_length()?;
+                }
+                OPCODE_SELECTDB => db = self.read_length()?,
+                OPCODE_EXPIRETIME => {
+                    let mut buf = [0; 4];
+                    self.read_exact(&mut buf)?;
+                    expire_at_ms = Some(u32::from_le_bytes(buf) as i64 * 1000);
+                }
+                OPCODE_EXPIRETIME_MS => {
+                    let mut buf = [0; 8];
+                    self.read_exact(&mut buf)?;
+                    expire_at_ms = Some(i64::from_le_bytes(buf));
+                }
+                OPCODE_IDLE => {
+                    self.read_length()?;
+                }
+                OPCODE_FREQ => {
+                    self.read_u8()?;
+                }
+                OPCODE_SLOT_INFO => {
+                    for _ in 0..3 {
+                        self.read_length()?;
+                    }
+                }
+                OPCODE_FUNCTION2 => {
+                    self.read_raw_string()?;
+                }
+                OPCODE_MODULE_AUX => {
+                    // Module id, the "when" marker and its value, then the module's own fields
+                    for _ in 0..3 {
+                        self.read_length()?;
+                    }
+                    self.skip_module_fields()?;
+                }
+                OPCODE_FUNCTION_PRE_GA => return Err(AppError::UnsupportedRdbType(opcode)),
+                value_type => {
+                    let key = self.read_string()?;
+                    let value = self.read_value(&key, value_type)?;
+                    let expire_at = expire_at_ms.take();
+
+                    let Some(value) = value else {
+                        summary.skipped_keys += 1;
+                        continue;
+                    };
+                    if db != 0 {
+                        summary.skipped_keys += 1;
+                        continue;
+                    }
+
+                    let expires = match expire_at {
+                        Some(at) if at <= now_ms => {
+                            summary.expired_keys += 1;
+                            continue;
+                        }
+                        Some(at) => (at - now_ms) as usize,
+                        None => 0,
+                    };
+
+                    items.insert(key, Item { value, created: Instant::now(), expires });
+                    summary.loaded_keys += 1;
+                }
+            }
+        }
+
+        // Checksums were introduced in version 5; a stored checksum of zero means it was disabled
+        if summary.version >= 5 {
+            let expected = self.crc;
+            let mut buf = [0; 8];
+            self.reader.read_exact(&mut buf).map_err(AppError::FileError)?;
+            let checksum = u64::from_le_bytes(buf);
+            if checksum != 0 && checksum != expected {
+                return Err(AppError::ChecksumMismatch);
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+fn parse_score(bytes: &[u8]) -> Result<f64, AppError> {
+    let s = std::str::from_utf8(bytes).map_err(|_| AppError::InvalidFileFormat)?;
+    match s {
+        "inf" | "+inf" => Ok(f64::INFINITY),
+        "-inf" => Ok(f64::NEG_INFINITY),
+        _ => s.parse::<f64>().map_err(|_| AppError::InvalidFileFormat),
+    }
+}
+
+fn take<'a>(blob: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], AppError> {
+    let slice = blob.get(*pos..*pos + len).ok_or(AppError::InvalidFileFormat)?;
+    *pos += len;
+    Ok(slice)
+}
+
+fn take_int_le(blob: &[u8], pos: &mut usize, len: usize) -> Result<i64, AppError> {
+    let bytes = take(blob, pos, len)?;
+    let mut buf = [0; 8];
+    buf[..len].copy_from_slice(bytes);
+    // Sign-extend from the most significant byte that was read
+    let shift = 64 - len * 8;
+    Ok((i64::from_le_bytes(buf) << shift) >> shift)
+}
+
+/// Decodes the entries of a ziplist blob (Redis < 7 compact encoding).
+fn parse_ziplist(blob: &[u8]) -> Result<Vec<Vec<u8>>, AppError> {
+    let mut pos = 10;
+    let mut entries = Vec::new();
+
+    loop {
+        let prevlen = *blob.get(pos).ok_or(AppError::InvalidFileFormat)?;
+        if prevlen == 0xFF {
+            break;
+        }
+        pos += if prevlen == 0xFE { 5 } else { 1 };
+
+        let encoding = *blob.get(pos).ok_or(AppError::InvalidFileFormat)?;
+        pos += 1;
+        let entry = match encoding >> 6 {
+            0 => take(blob, &mut pos, (encoding & 0x3f) as usize)?.to_vec(),
+            1 => {
+                let len = (((encoding & 0x3f) as usize) << 8) | take(blob, &mut pos, 1)?[0] as usize;
+                take(blob, &mut pos, len)?.to_vec()
+            }
+            2 => {
+                let len = u32::from_be_bytes(take(blob, &mut pos, 4)?.try_into().unwrap()) as usize;
+                take(blob, &mut pos, len)?.to_vec()
+            }
+            _ => {
+                let value = match encoding {
+                    0xC0 => take_int_le(blob, &mut pos, 2)?,
+                    0xD0 => take_int_le(blob, &mut pos, 4)?,
+                    0xE0 => take_int_le(blob, &mut pos, 8)?,
+                    0xF0 => take_int_le(blob, &mut pos, 3)?,
+                    0xFE => take_int_le(blob, &mut pos, 1)?,
+                    0xF1..=0xFD => (encoding & 0x0f) as i64 - 1,
+                    _ => return Err(AppError::InvalidFileFormat),
+                };
+                value.to_string().into_bytes()
+            }
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Decodes the entries of a listpack blob (Redis 7 compact encoding).
+fn parse_listpack(blob: &[u8]) -> Result<Vec<Vec<u8>>, AppError> {
+    let mut pos = 6;
+    let mut entries = Vec::new();
+
+    loop {
+        let start = pos;
+        let encoding = *blob.get(pos).ok_or(AppError::InvalidFileFormat)?;
+        if encoding == 0xFF {
+            break;
+        }
+        pos += 1;
+
+        let entry = if encoding & 0x80 == 0 {
+            (encoding & 0x7f).to_string().into_bytes()
+        } else if encoding & 0xC0 == 0x80 {
+            take(blob, &mut pos, (encoding & 0x3f) as usize)?.to_vec()
+        } else if encoding & 0xE0 == 0xC0 {
+            let raw = (((encoding & 0x1f) as i64) << 8) | take(blob, &mut pos, 1)?[0] as i64;
+            let value = if raw >= 1 << 12 { raw - (1 << 13) } else { raw };
+            value.to_string().into_bytes()
+        } else if encoding & 0xF0 == 0xE0 {
+            let len = (((encoding & 0x0f) as usize) << 8) | take(blob, &mut pos, 1)?[0] as usize;
+            take(blob, &mut pos, len)?.to_vec()
+        } else {
+            match encoding {
+                0xF0 => {
+                    let len = u32::from_le_bytes(take(blob, &mut pos, 4)?.try_into().unwrap()) as usize;
+                    take(blob, &mut pos, len)?.to_vec()
+                }
+                0xF1 => take_int_le(blob, &mut pos, 2)?.to_string().into_bytes(),
+                0xF2 => take_int_le(blob, &mut pos, 3)?.to_string().into_bytes(),
+                0xF3 => take_int_le(blob, &mut pos, 4)?.to_string().into_bytes(),
+                0xF4 => take_int_le(blob, &mut pos, 8)?.to_string().into_bytes(),
+                _ => return Err(AppError::InvalidFileFormat),
+            }
+        };
+
+        // Skip the backlen, whose size depends on the length of the entry it trails
+        let entry_len = pos - start;
+        pos += match entry_len {
+            0..=127 => 1,
+            128..=16382 => 2,
+            16383..=2097150 => 3,
+            2097151..=268435454 => 4,
+            _ => 5,
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut bytes = vec![s.len() as u8];
+        bytes.extend_from_slice(s.as_bytes());
+        bytes
+    }
+
+    /// A listpack holding `a 1 b 2`, the encoding Redis 7 uses for small sorted sets.
+    fn zset_listpack() -> Vec<u8> {
+        let entries = [0x81, b'a', 0x02, 0x01, 0x01, 0x81, b'b', 0x02, 0x02, 0x01, 0xFF];
+        let mut blob = ((6 + entries.len()) as u32).to_le_bytes().to_vec();
+        blob.extend_from_slice(&4u16.to_le_bytes());
+        blob.extend_from_slice(&entries);
+        blob
+    }
+
+    /// A small RDB file laid out the way Redis 7.2 writes it, ending with its checksum.
+    fn fixture() -> Vec<u8> {
+        let mut rdb = b"REDIS0011".to_vec();
+        rdb.push(OPCODE_AUX);
+        rdb.extend(string("redis-ver"));
+        rdb.extend(string("7.2.4"));
+        rdb.push(OPCODE_AUX);
+        rdb.extend(string("ctime"));
+        rdb.extend([0xC2, 0x00, 0xE1, 0xF5, 0x05]);
+        // A module aux field with an unsigned integer and a string, which must be skipped
+        rdb.extend([OPCODE_MODULE_AUX, 0x05, 0x02, 0x02, 0x02, 0x2A, 0x05, 0x02, b'o', b'k', 0x00]);
+        rdb.extend([OPCODE_SELECTDB, 0x00, OPCODE_RESIZEDB, 0x04, 0x01]);
+        rdb.push(TYPE_STRING);
+        rdb.extend(string("foo"));
+        rdb.extend(string("bar"));
+        rdb.push(OPCODE_EXPIRETIME_MS);
+        rdb.extend(4_102_444_800_000i64.to_le_bytes());
+        rdb.push(TYPE_STRING);
+        rdb.extend(string("later"));
+        rdb.extend([0xC0, 0x7B]);
+        rdb.push(OPCODE_EXPIRETIME_MS);
+        rdb.extend(1_000i64.to_le_bytes());
+        rdb.push(TYPE_STRING);
+        rdb.extend(string("gone"));
+        rdb.extend(string("x"));
+        rdb.push(TYPE_ZSET_LISTPACK);
+        rdb.extend(string("rank"));
+        let blob = zset_listpack();
+        rdb.push(blob.len() as u8);
+        rdb.extend(blob);
+        rdb.push(OPCODE_EOF);
+        let checksum = crc64::update(0, &rdb);
+        rdb.extend(checksum.to_le_bytes());
+        rdb
+    }
+
+    #[test]
+    fn loads_a_real_file() {
+        let mut items = Keyspace::new();
+        let summary = RdbReader::new(&fixture()[..]).load(&mut items).unwrap();
+
+        assert_eq!(summary.version, 11);
+        assert_eq!(summary.aux.get("redis-ver").map(String::as_str), Some("7.2.4"));
+        assert_eq!(summary.aux.get("ctime").map(String::as_str), Some("100000000"));
+        assert_eq!((summary.loaded_keys, summary.expired_keys, summary.skipped_keys), (3, 1, 0));
+
+        assert!(matches!(&items.get("foo").unwrap().value, Value::String(s) if s == "bar"));
+        let later = items.get("later").unwrap();
+        assert!(matches!(&later.value, Value::String(s) if s == "123"));
+        assert!(later.expires > 0);
+        assert!(items.get("gone").is_none());
+        let Value::SortedSet(set) = &items.get("rank").unwrap().value else { panic!("expected a sorted set") };
+        assert_eq!((set.score("a"), set.score("b")), (Some(1.0), Some(2.0)));
+    }
+
+    #[test]
+    fn rejects_a_corrupted_checksum() {
+        let mut rdb = fixture();
+        let last = rdb.len() - 1;
+        rdb[last] ^= 0xFF;
+        assert!(matches!(RdbReader::new(&rdb[..]).load(&mut Keyspace::new()), Err(AppError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn reads_values_by_type() {
+        let mut blob = vec![zset_listpack().len() as u8];
+        blob.extend(zset_listpack());
+        let value = RdbReader::new(&blob[..]).read_value("rank", TYPE_ZSET_LISTPACK).unwrap();
+        assert!(matches!(value, Some(Value::SortedSet(set)) if set.len() == 2));
+
+        // A plain hash is consumed but not represented
+        let mut reader = RdbReader::new(&[0x01, 0x01, b'f', 0x01, b'v', 0xFF][..]);
+        assert!(reader.read_value("h", TYPE_HASH).unwrap().is_none());
+        assert_eq!(reader.read_u8().unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn names_the_key_of_a_value_it_cannot_skip() {
+        let err = RdbReader::new(&[0x00][..]).read_value("events", TYPE_STREAM_LISTPACKS_3).unwrap_err();
+        assert!(matches!(&err, AppError::UnsupportedRdbValue(key, TYPE_STREAM_LISTPACKS_3) if key == "events"));
+        assert_eq!(err.to_string(), "ERR cannot load key 'events': unsupported RDB stream value (type 21)");
+    }
+
+    #[test]
+    fn does_not_trust_declared_lengths() {
+        // Claims a 4 GiB string but holds three bytes
+        let mut reader = RdbReader::new(&[0x80, 0xFF, 0xFF, 0xFF, 0xFF, b'a', b'b', b'c'][..]);
+        assert!(matches!(reader.read_raw_string(), Err(AppError::FileError(e)) if e.kind() == ErrorKind::UnexpectedEof));
+    }
+}
@@ -0,0 +1,158 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::constants::REDIS_VERSION;
+use crate::enums::value::Value;
+use crate::errors::app_errors::AppError;
+use crate::rdb::*;
+use crate::storage::Item;
+use crate::types::keyspace::Keyspace;
+use crate::utils::atomic_file::write_atomically;
+
+/// Strings shorter than this are never worth compressing.
+const MIN_COMPRESS_LEN: usize = 20;
+
+pub struct RdbWriter<W: Write> {
+    writer: W,
+    crc: u64,
+    compression: bool,
+}
+
+impl<W: Write> RdbWriter<W> {
+    pub fn new(writer: W, compression: bool) -> Self {
+        RdbWriter { writer, crc: 0, compression }
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), AppError> {
+        self.writer.write_all(bytes).map_err(AppError::FileError)?;
+        self.crc = crc64::update(self.crc, bytes);
+        Ok(())
+    }
+
+    fn write_length(&mut self, len: u64) -> Result<(), AppError> {
+        if len < 1 << 6 {
+            self.write_all(&[len as u8])
+        } else if len < 1 << 14 {
+            self.write_all(&[((len >> 8) as u8) | 0x40, len as u8])
+        } else if len <= u32::MAX as u64 {
+            self.write_all(&[0x80])?;
+            self.write_all(&(len as u32).to_be_bytes())
+        } else {
+            self.write_all(&[0x81])?;
+            self.write_all(&len.to_be_bytes())
+        }
+    }
+
+    /// Writes `value` as a canonical integer if it round-trips through one, which is how Redis stores numeric strings.
+    fn try_write_integer(&mut self, value: &str) -> Result<bool, AppError> {
+        let Ok(int) = value.parse::<i32>() else { return Ok(false) };
+        if int.to_string() != value {
+            return Ok(false);
+        }
+
+        if let Ok(int) = i8::try_from(int) {
+            self.write_all(&[0xC0 | ENC_INT8, int as u8])?;
+        } else if let Ok(int) = i16::try_from(int) {
+            self.write_all(&[0xC0 | ENC_INT16])?;
+            self.write_all(&int.to_le_bytes())?;
+        } else {
+            self.write_all(&[0xC0 | ENC_INT32])?;
+            self.write_all(&int.to_le_bytes())?;
+        }
+        Ok(true)
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<(), AppError> {
+        if value.len() <= 11 && self.try_write_integer(value)? {
+            return Ok(());
+        }
+
+        let bytes = value.as_bytes();
+        if self.compression && bytes.len() > MIN_COMPRESS_LEN {
+            if let Some(compressed) = lzf::compress(bytes, bytes.len() - 4) {
+                self.write_all(&[0xC0 | ENC_LZF])?;
+                self.write_length(compressed.len() as u64)?;
+                self.write_length(bytes.len() as u64)?;
+                return self.write_all(&compressed);
+            }
+        }
+
+        self.write_length(bytes.len() as u64)?;
+        self.write_all(bytes)
+    }
+
+    fn write_aux(&mut self, key: &str, value: &str) -> Result<(), AppError> {
+        self.write_all(&[OPCODE_AUX])?;
+        self.write_string(key)?;
+        self.write_string(value)
+    }
+
+    /// Serializes every live item as an RDB file, including the trailing checksum.
+    pub fn save(mut self, items: &Keyspace) -> Result<W, AppError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let now_ms = now.as_millis() as u64;
+        let used_memory: usize = items.iter().map(|(key, item)| key.len() + item.value.estimated_size()).sum();
+
+        self.write_all(format!("REDIS{:04}", RDB_VERSION).as_bytes())?;
+        self.write_aux("redis-ver", REDIS_VERSION)?;
+        self.write_aux("redis-bits", &(usize::BITS).to_string())?;
+        self.write_aux("ctime", &now.as_secs().to_string())?;
+        self.write_aux("used-mem", &used_memory.to_string())?;
+        self.write_aux("aof-base", "0")?;
+
+        let live: Vec<(&String, &Item, Option<u64>)> = items
+            .iter()
+            .filter_map(|(key, item)| {
+                if item.expires == 0 {
+                    return Some((key, item, None));
+                }
+                let elapsed = item.created.elapsed().as_millis() as u64;
+                let remaining = (item.expires as u64).checked_sub(elapsed)?;
+                Some((key, item, Some(now_ms + remaining)))
+            })
+            .collect();
+        let expires = live.iter().filter(|(_, _, expire_at)| expire_at.is_some()).count();
+
+        self.write_all(&[OPCODE_SELECTDB])?;
+        self.write_length(0)?;
+        self.write_all(&[OPCODE_RESIZEDB])?;
+        self.write_length(live.len() as u64)?;
+        self.write_length(expires as u64)?;
+
+        for (key, item, expire_at) in live {
+            if let Some(expire_at) = expire_at {
+                self.write_all(&[OPCODE_EXPIRETIME_MS])?;
+                self.write_all(&expire_at.to_le_bytes())?;
+            }
+
+            match &item.value {
+                Value::String(value) => {
+                    self.write_all(&[TYPE_STRING])?;
+                    self.write_string(key)?;
+                    self.write_string(value)?;
+                }
+                Value::SortedSet(set) => {
+                    self.write_all(&[TYPE_ZSET_2])?;
+                    self.write_string(key)?;
+                    self.write_length(set.len() as u64)?;
+                    for (member, score) in set.iter() {
+                        self.write_string(member)?;
+                        self.write_all(&score.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+
+        self.write_all(&[OPCODE_EOF])?;
+        let checksum = self.crc;
+        self.writer.write_all(&checksum.to_le_bytes()).map_err(AppError::FileError)?;
+        self.writer.flush().map_err(AppError::FileError)?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Atomically replaces the RDB file at `path` with the contents of `items`.
+pub fn save_rdb(path: &Path, items: &Keyspace, compression: bool) -> Result<(), AppError> {
+    write_atomically(path, |writer| RdbWriter::new(writer, compression).save(items).map(|_| ()))
+}
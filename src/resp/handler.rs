@@ -1,57 +1,247 @@
-use bytes::BytesMut;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use bytes::{Buf, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
 use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+use crate::config::buffer_limits::OutputBufferLimit;
 use crate::resp::parser::Parser;
 
-pub struct RespHandler {
-    stream: TcpStream,
+/// Multibulk headers and inline commands longer than this without a line ending are refused.
+const MAX_INLINE_LEN: usize = 64 * 1024;
+/// How much room is made in the query buffer before each read.
+const READ_CHUNK_LEN: usize = 16 * 1024;
+/// The most arguments a single command may have.
+const MAX_MULTIBULK_LEN: i64 = 1024 * 1024;
+
+/// Traffic generated by a connection since the counters were last taken.
+#[derive(Debug, Default)]
+pub struct IoCounters {
+    pub read: u64,
+    pub written: u64,
+    pub error_prefixes: Vec<String>,
+}
+
+/// Any byte stream a client can be served over, such as a TCP or Unix socket.
+pub trait ClientStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ClientStream for T {}
+
+/// Buffer limits a connection enforces. A limit of 0 is disabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BufferLimits {
+    pub output: OutputBufferLimit,
+    pub query_buffer: u64,
+    pub max_bulk_len: u64,
+}
+
+/// Reads commands and buffers replies. Replies are written out while the connection waits for
+/// its next command, so a client that does not read them only grows its own output buffer,
+/// until it goes over the limits and gets disconnected.
+///
+/// Errors of kind `InvalidData` mean the client broke the protocol or a buffer limit.
+pub struct RespHandler<S: ClientStream> {
+    reader: ReadHalf<S>,
+    writer: WriteHalf<S>,
     buffer: BytesMut,
+    output: BytesMut,
+    limits: BufferLimits,
+    /// When the output buffer went over the soft limit, while it stays over it.
+    soft_limit_reached: Option<Instant>,
+    counters: IoCounters,
 }
 
-impl RespHandler {
-    pub fn new(stream: TcpStream) -> Self {
+impl<S: ClientStream> RespHandler<S> {
+    pub fn new(stream: S) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
         RespHandler {
-            stream,
+            reader,
+            writer,
             buffer: BytesMut::with_capacity(512),
+            output: BytesMut::new(),
+            limits: BufferLimits::default(),
+            soft_limit_reached: None,
+            counters: IoCounters::default(),
         }
     }
 
+    pub fn set_limits(&mut self, limits: BufferLimits) {
+        self.limits = limits;
+    }
+
     pub async fn get_command_with_args(&mut self) -> Result<(String, Vec<String>), Error> {
-        let bytes_read = self.stream.read_buf(&mut self.buffer).await?;
-        let mut command = String::new();
-        let mut args = Vec::new();
+        loop {
+            match parse_command(&self.buffer, self.limits.max_bulk_len) {
+                Ok(Some((mut args, used))) => {
+                    self.buffer.advance(used);
+                    // Empty commands are skipped, as blank lines are
+                    if args.is_empty() {
+                        continue;
+                    }
+                    let command = args.remove(0).to_lowercase();
+                    return Ok((command, args));
+                }
+                Ok(None) => {}
+                Err(reason) => {
+                    self.buffer.clear();
+                    self.response(Parser::SimpleError(format!("ERR {}", reason))).await?;
+                    return Err(Error::new(ErrorKind::InvalidData, reason));
+                }
+            }
 
-        if bytes_read == 0 {
-            return Err(Error::new(ErrorKind::UnexpectedEof, "No data read from stream"));
+            if self.limits.query_buffer > 0 && self.buffer.len() as u64 > self.limits.query_buffer {
+                return Err(Error::new(ErrorKind::InvalidData, "query buffer limit reached"));
+            }
+            self.fill_buffer().await?;
         }
+    }
 
-        let line = String::from_utf8_lossy(&self.buffer);
-
-        if let Some(num_args) = line.strip_prefix('*').and_then(|s| s.split("\r\n").next()?.parse::<usize>().ok()) {
-            let mut parts = line.split("\r\n").skip(1);
+    /// Reads more of the query, writing pending replies out in the meantime.
+    async fn fill_buffer(&mut self) -> Result<(), Error> {
+        enum Progress {
+            Read(usize),
+            Written(usize),
+        }
 
-            for _ in 0..num_args {
-                if let Some(arg_len) = parts.next() {
-                    if let Some(arg) = parts.next() {
-                        if arg_len.starts_with('$') {
-                            args.push(arg.to_string());
-                        }
-                    }
+        self.buffer.reserve(READ_CHUNK_LEN);
+        loop {
+            let progress = tokio::select! {
+                read = self.reader.read_buf(&mut self.buffer) => Progress::Read(read?),
+                written = self.writer.write(&self.output), if !self.output.is_empty() => Progress::Written(written?),
+            };
+            match progress {
+                Progress::Read(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "No data read from stream")),
+                Progress::Read(read) => {
+                    self.counters.read += read as u64;
+                    return Ok(());
+                }
+                Progress::Written(0) => return Err(Error::from(ErrorKind::WriteZero)),
+                Progress::Written(written) => {
+                    self.output.advance(written);
+                    self.counters.written += written as u64;
                 }
             }
+        }
+    }
 
-            if !args.is_empty() {
-                command = args.remove(0).to_lowercase();
+    /// Queues a reply. Fails if that takes the output buffer over its limits.
+    pub async fn response(&mut self, value: Parser) -> Result<(), Error> {
+        if let Parser::SimpleError(message) = &value {
+            let prefix = message.split(' ').next().unwrap_or_default();
+            self.counters.error_prefixes.push(prefix.to_string());
+        }
+        self.output.extend_from_slice(value.serialize().as_bytes());
+        self.check_output_limits()
+    }
+
+    /// Pending replies are dropped when a limit is reached, as the client is disconnected anyway.
+    fn check_output_limits(&mut self) -> Result<(), Error> {
+        let used = self.output.len() as u64;
+        let limit = self.limits.output;
+        let mut reached = None;
+        if limit.hard > 0 && used > limit.hard {
+            reached = Some("output buffer hard limit reached");
+        } else if limit.soft > 0 && used > limit.soft {
+            let since = *self.soft_limit_reached.get_or_insert_with(Instant::now);
+            if since.elapsed() >= Duration::from_secs(limit.soft_seconds) {
+                reached = Some("output buffer soft limit reached");
+            }
+        } else {
+            self.soft_limit_reached = None;
+        }
+
+        match reached {
+            Some(reason) => {
+                self.output.clear();
+                Err(Error::new(ErrorKind::InvalidData, reason))
             }
+            None => Ok(()),
         }
-        self.buffer.clear();
+    }
 
-        Ok((command, args))
+    /// Writes every pending reply out.
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        self.writer.write_all(&self.output).await?;
+        self.counters.written += self.output.len() as u64;
+        self.output.clear();
+        self.writer.flush().await
     }
 
-    pub async fn response(&mut self, value: Parser) -> Result<(), Error> {
-        self.stream.write_all(value.serialize().as_bytes()).await?;
-        Ok(())
+    /// Bytes waiting in the query buffer and its allocated capacity.
+    pub fn query_buffer(&self) -> (usize, usize) {
+        (self.buffer.len(), self.buffer.capacity())
+    }
+
+    /// Bytes of replies not written out yet.
+    pub fn output_buffer(&self) -> usize {
+        self.output.len()
+    }
+
+    pub fn take_counters(&mut self) -> IoCounters {
+        std::mem::take(&mut self.counters)
+    }
+}
+
+/// The line starting at `start`, without its `\r\n`, and where the next one starts.
+fn read_line(buffer: &[u8], start: usize) -> Option<(&[u8], usize)> {
+    let end = buffer[start..].windows(2).position(|pair| pair == b"\r\n")? + start;
+    Some((&buffer[start..end], end + 2))
+}
+
+fn parse_integer(line: &[u8]) -> Option<i64> {
+    std::str::from_utf8(line).ok()?.parse::<i64>().ok()
+}
+
+/// Parses the command at the front of `buffer`, either a multibulk array of bulk strings or an
+/// inline command. Returns its arguments and the bytes it took up, or `None` until all of it
+/// arrived. Errors are protocol errors, after which the connection is closed.
+fn parse_command(buffer: &[u8], max_bulk_len: u64) -> Result<Option<(Vec<String>, usize)>, String> {
+    if buffer.is_empty() {
+        return Ok(None);
+    }
+    if buffer[0] != b'*' {
+        let Some(end) = buffer.iter().position(|byte| *byte == b'\n') else {
+            return match buffer.len() > MAX_INLINE_LEN {
+                true => Err("Protocol error: too big inline request".to_string()),
+                false => Ok(None),
+            };
+        };
+        let line = String::from_utf8_lossy(&buffer[..end]);
+        return Ok(Some((line.split_whitespace().map(str::to_string).collect(), end + 1)));
+    }
+
+    let Some((header, mut position)) = read_line(buffer, 1) else {
+        return match buffer.len() > MAX_INLINE_LEN {
+            true => Err("Protocol error: too big mbulk count string".to_string()),
+            false => Ok(None),
+        };
+    };
+    let count = match parse_integer(header) {
+        Some(count) if count <= MAX_MULTIBULK_LEN => count,
+        _ => return Err("Protocol error: invalid multibulk length".to_string()),
+    };
+
+    let mut args = Vec::new();
+    for _ in 0..count.max(0) {
+        let Some(&marker) = buffer.get(position) else {
+            return Ok(None);
+        };
+        if marker != b'$' {
+            return Err(format!("Protocol error: expected '$', got '{}'", marker as char));
+        }
+        let Some((header, start)) = read_line(buffer, position + 1) else {
+            return match buffer.len() - position > MAX_INLINE_LEN {
+                true => Err("Protocol error: too big bulk count string".to_string()),
+                false => Ok(None),
+            };
+        };
+        let len = match parse_integer(header) {
+            Some(len) if len >= 0 && (max_bulk_len == 0 || len as u64 <= max_bulk_len) => len as usize,
+            _ => return Err("Protocol error: invalid bulk length".to_string()),
+        };
+        if buffer.len() < start + len + 2 {
+            return Ok(None);
+        }
+        args.push(String::from_utf8_lossy(&buffer[start..start + len]).to_string());
+        position = start + len + 2;
     }
+    Ok(Some((args, position)))
 }
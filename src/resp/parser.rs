@@ -1,3 +1,4 @@
+use std::time::{SystemTime, UNIX_EPOCH};
 use crate::enums::sign::Sign;
 use crate::errors::app_errors::AppError;
 
@@ -7,7 +8,9 @@ pub enum Parser {
     BulkString(String),
     NullBulkString,
     Array(Vec<String>),
-    Integer(Option<Sign>, u16),
+    NestedArray(Vec<Parser>),
+    NullArray,
+    Integer(Option<Sign>, u64),
 }
 
 impl Parser {
@@ -17,6 +20,8 @@ impl Parser {
             Parser::SimpleError(s) => format!("-{}\r\n", s),
             Parser::NullBulkString => "$-1\r\n".to_string(),
             Parser::Array(v) => self.get_array_string(v),
+            Parser::NestedArray(v) => self.get_nested_array_string(v),
+            Parser::NullArray => "*-1\r\n".to_string(),
             Parser::BulkString(s) => format!("${}\r\n{}\r\n", s.len(), s),
             Parser::Integer(s, i) => self.get_integer_string(s, *i),
         }
@@ -30,7 +35,15 @@ impl Parser {
         array_string
     }
 
-    fn get_integer_string(&self, sign: &Option<Sign>, i: u16) -> String {
+    fn get_nested_array_string(&self, v: &Vec<Parser>) -> String {
+        let mut array_string = format!("*{}\r\n", v.len());
+        for p in v {
+            array_string.push_str(&p.serialize());
+        }
+        array_string
+    }
+
+    fn get_integer_string(&self, sign: &Option<Sign>, i: u64) -> String {
         match sign {
             Some(sign) => format!(":{:?}{}\r\n", sign, i),
             None => format!(":{}\r\n", i),
@@ -45,12 +58,28 @@ pub async fn extract_set_command_args(args: Vec<String>) -> Result<(String, Stri
 
     let key = args[0].clone();
     let value = args[1].clone();
-    let expiration = if args.len() > 3 && args[2] == "px" {
-        match args[3].parse() {
-            Ok(exp) => exp,
-            Err(_) => {
-                return Err(AppError::InvalidExpirationValue);
-            }
+    let expiration = if args.len() > 3 {
+        let Ok(exp) = args[3].parse::<i64>() else {
+            return Err(AppError::InvalidExpirationValue);
+        };
+        if exp <= 0 {
+            return Err(AppError::InvalidExpireTime("set".to_string()));
+        }
+        let exp = exp as u64;
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+
+        // Absolute expirations are converted to a relative one, with keys already past it expiring immediately
+        let relative = match args[2].to_lowercase().as_str() {
+            "px" => Some(exp),
+            "ex" => exp.checked_mul(1000),
+            "pxat" => Some(exp.saturating_sub(now_ms).max(1)),
+            "exat" => exp.checked_mul(1000).map(|at| at.saturating_sub(now_ms).max(1)),
+            _ => return Err(AppError::SyntaxError),
+        };
+        // The deadline is later logged as an absolute time, so it has to fit as well
+        match relative.filter(|ms| now_ms.checked_add(*ms).is_some_and(|at| at <= i64::MAX as u64)) {
+            Some(ms) => ms as usize,
+            None => return Err(AppError::InvalidExpirationValue),
         }
     } else {
         0
@@ -0,0 +1,164 @@
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpSocket, UnixListener};
+use tokio::sync::Mutex;
+use crate::commands::handler::handle_connection;
+use crate::config::info_server::InfoServer;
+use crate::config::registry::parse_bind_address;
+use crate::config::server_config::ServerConfig;
+use crate::errors::app_errors::AppError;
+use crate::resp::handler::{ClientStream, RespHandler};
+use crate::resp::parser::Parser::SimpleError;
+use crate::servers::shutdown::{until_shutdown, until_shutdown_aborted};
+use crate::servers::sockopt;
+use crate::storage::Storage;
+
+/// Pending connections the kernel queues for each TCP listener, matching Redis' default.
+const TCP_BACKLOG: u32 = 511;
+
+/// A socket clients connect to.
+pub enum Listener {
+    Tcp(TcpListener),
+    /// A Unix socket and its path, which stands in for client addresses.
+    Unix(UnixListener, String),
+}
+
+/// Binds a TCP listener per `bind` address unless `port` is 0, and the Unix socket if
+/// `unixsocket` is set. Optional addresses that cannot be bound are skipped with a warning,
+/// e.g. IPv6 on a host without it.
+pub async fn bind_listeners(config: &ServerConfig) -> Result<Vec<Listener>, AppError> {
+    let mut listeners = Vec::new();
+
+    if config.port != 0 {
+        for address in &config.bind {
+            // Addresses were validated when the configuration was read
+            let Some((ip, optional)) = parse_bind_address(address) else {
+                continue;
+            };
+            let addr = SocketAddr::new(ip, config.port);
+            match bind_tcp(addr) {
+                Ok(listener) => listeners.push(Listener::Tcp(listener)),
+                Err(e) if optional => {
+                    log_warning!("Could not bind optional address {}: {}", addr, e);
+                }
+                Err(e) => return Err(AppError::ListenError(addr.to_string(), e)),
+            }
+        }
+    }
+
+    if let Some(path) = &config.unixsocket {
+        // A socket left behind by a previous run would make bind fail, anything else is left alone
+        if fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            let _ = fs::remove_file(path);
+        }
+        let listener = UnixListener::bind(path).map_err(|e| AppError::ListenError(path.clone(), e))?;
+        if config.unixsocketperm != 0 {
+            fs::set_permissions(path, fs::Permissions::from_mode(config.unixsocketperm))
+                .map_err(|e| AppError::ListenError(path.clone(), e))?;
+        }
+        listeners.push(Listener::Unix(listener, path.clone()));
+    }
+
+    if listeners.is_empty() {
+        return Err(AppError::NoListeners);
+    }
+    Ok(listeners)
+}
+
+/// Binds a TCP listener. IPv6 sockets are made v6-only, as Redis does, so `::` does not also
+/// claim IPv4 and `bind * ::*` can hold the same port on both.
+fn bind_tcp(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => {
+            let socket = TcpSocket::new_v6()?;
+            sockopt::set_only_v6(&socket)?;
+            socket
+        }
+    };
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(TCP_BACKLOG)
+}
+
+/// Accepts clients forever, serving each one on its own task.
+pub async fn accept_clients(listener: Listener, storage: Arc<Mutex<Storage>>, info_server: Arc<Mutex<InfoServer>>) {
+    let mut shutting_down = info_server.lock().await.shutting_down.subscribe();
+    loop {
+        // Clients connecting during a shutdown wait in the backlog until it completes or fails
+        tokio::select! {
+            _ = until_shutdown(&mut shutting_down) => {
+                until_shutdown_aborted(&mut shutting_down).await;
+                continue;
+            }
+            _ = accept_next(&listener, &storage, &info_server) => {}
+        }
+    }
+}
+
+/// Accepts one client. Accept errors are logged and otherwise ignored.
+async fn accept_next(listener: &Listener, storage: &Arc<Mutex<Storage>>, info_server: &Arc<Mutex<InfoServer>>) {
+    let accepted = match listener {
+        Listener::Tcp(listener) => {
+            // Read before accepting, so no lock is awaited while holding an accepted stream
+            let keepalive = storage.lock().await.config.tcp_keepalive;
+            listener.accept().await.and_then(|(stream, addr)| {
+                if keepalive != 0 {
+                    sockopt::set_keepalive(&stream, keepalive)?;
+                }
+                let laddr = stream.local_addr().map(|laddr| laddr.to_string()).unwrap_or_default();
+                let local = addr.ip().to_canonical().is_loopback();
+                spawn_client(stream, addr.to_string(), laddr, local, storage, info_server);
+                Ok(())
+            })
+        }
+        Listener::Unix(listener, path) => listener.accept().await.map(|(stream, _)| {
+            let addr = format!("{}:0", path);
+            spawn_client(stream, addr.clone(), addr, true, storage, info_server)
+        }),
+    };
+
+    if let Err(e) = accepted {
+        log_warning!("Error accepting client: {}", e)
+    }
+}
+
+/// Whether protected mode keeps out non-local clients: it is enabled and the default user
+/// needs no password.
+async fn refuses_remote_clients(storage: &Arc<Mutex<Storage>>, info_server: &Arc<Mutex<InfoServer>>) -> bool {
+    let protected_mode = storage.lock().await.config.protected_mode;
+    protected_mode && !info_server.lock().await.acl.auth_required()
+}
+
+/// Serves a client on its own task. `local` clients, connected over loopback or the Unix
+/// socket, are never refused by protected mode.
+fn spawn_client<S: ClientStream + 'static>(
+    stream: S,
+    addr: String,
+    laddr: String,
+    local: bool,
+    storage: &Arc<Mutex<Storage>>,
+    info_server: &Arc<Mutex<InfoServer>>,
+) {
+    log_verbose!("Accepted connection from {addr}");
+    let storage = Arc::clone(storage);
+    let info_server = Arc::clone(info_server);
+    tokio::spawn(async move {
+        info_server.lock().await.stats.total_connections_received += 1;
+        if !local && refuses_remote_clients(&storage, &info_server).await {
+            let mut handler = RespHandler::new(stream);
+            // The client is dropped right away, so failing to tell it why is not an error
+            if handler.response(SimpleError(AppError::ProtectedMode.to_string())).await.is_ok() {
+                let _ = handler.flush().await;
+            }
+            return;
+        }
+        // A client vanishing mid-conversation, e.g. with a connection reset, only ends its own task
+        if let Err(e) = handle_connection(stream, addr.clone(), laddr, storage, info_server).await {
+            log_verbose!("Error serving client {}: {}", addr, e);
+        }
+    });
+}
@@ -1 +1,4 @@
 pub mod replication;
+pub mod listener;
+pub mod shutdown;
+pub mod sockopt;
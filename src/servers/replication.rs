@@ -13,11 +13,11 @@ macro_rules! send_command {
         $self.buffer.clear();
         let bytes_read = $self.stream.read_buf(&mut $self.buffer).await.unwrap();
         if bytes_read == 0 {
-            println!("No message provided from master");
+            log_warning!("No message provided from master");
         }
         let line = String::from_utf8_lossy(&$self.buffer[..]);
         let line_printable = line.replace("\r\n", "\\r\\n");
-        println!("Response received from master: {}", line_printable);
+        log_verbose!("Response received from master: {}", line_printable);
         line
     }};
 }
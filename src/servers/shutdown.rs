@@ -0,0 +1,142 @@
+use std::fs;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use crate::config::info_server::InfoServer;
+use crate::errors::app_errors::AppError;
+use crate::storage::Storage;
+
+/// Whether SHUTDOWN writes a final RDB snapshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShutdownSave {
+    /// Save only if save points are configured.
+    Default,
+    Save,
+    NoSave,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownOptions {
+    pub save: ShutdownSave,
+    /// Exit even if the final save or AOF flush fails.
+    pub force: bool,
+}
+
+impl Default for ShutdownOptions {
+    fn default() -> Self {
+        ShutdownOptions { save: ShutdownSave::Default, force: false }
+    }
+}
+
+/// What a SHUTDOWN command asks for.
+pub enum ShutdownRequest {
+    Shutdown(ShutdownOptions),
+    Abort,
+}
+
+/// Parses `SHUTDOWN [NOSAVE|SAVE] [NOW] [FORCE] [ABORT]`. NOW is accepted and changes nothing:
+/// it skips waiting for lagging replicas, but replicas are not fed a replication stream and are
+/// never waited for.
+pub fn parse_shutdown_args(args: &[String]) -> Result<ShutdownRequest, AppError> {
+    let mut options = ShutdownOptions::default();
+    let mut abort = false;
+    for arg in args {
+        match arg.to_lowercase().as_str() {
+            "nosave" if options.save != ShutdownSave::Save => options.save = ShutdownSave::NoSave,
+            "save" if options.save != ShutdownSave::NoSave => options.save = ShutdownSave::Save,
+            "now" => {}
+            "force" => options.force = true,
+            "abort" => abort = true,
+            _ => return Err(AppError::SyntaxError),
+        }
+    }
+
+    match abort {
+        true if args.len() > 1 => Err(AppError::SyntaxError),
+        true => Ok(ShutdownRequest::Abort),
+        false => Ok(ShutdownRequest::Shutdown(options)),
+    }
+}
+
+/// Resolves once a shutdown started, or right away if one is already running.
+pub async fn until_shutdown(shutting_down: &mut watch::Receiver<bool>) {
+    let _ = shutting_down.wait_for(|shutting_down| *shutting_down).await;
+}
+
+/// Resolves once a shutdown that started was aborted.
+pub async fn until_shutdown_aborted(shutting_down: &mut watch::Receiver<bool>) {
+    let _ = shutting_down.wait_for(|shutting_down| !*shutting_down).await;
+}
+
+/// Stops accepting clients, writes the final snapshot once a running background save finished
+/// writing, flushes the AOF and exits. Only returns
+/// when that failed and was not forced, in which case the server goes back to accepting
+/// clients and keeps running.
+pub async fn shutdown(options: ShutdownOptions, storage: &Arc<Mutex<Storage>>, info_server: &Arc<Mutex<InfoServer>>) -> AppError {
+    let (shutting_down, replicas) = {
+        let info_server = info_server.lock().await;
+        (info_server.shutting_down.clone(), info_server.clients.iter().filter(|client| client.replica).count())
+    };
+    shutting_down.send_replace(true);
+    log_notice!("User requested shutdown...");
+    if replicas > 0 {
+        log_warning!("Not waiting for {} connected replica(s), which are not fed a replication stream", replicas);
+    }
+
+    // The storage lock is held until the process exits, so no command runs after the final save
+    let mut storage = storage.lock().await;
+    let save = match options.save {
+        ShutdownSave::Default => !storage.config.save_points.is_empty(),
+        ShutdownSave::Save => true,
+        ShutdownSave::NoSave => false,
+    };
+
+    let mut result = Ok(());
+    if save {
+        // Saving alongside it could let the older background snapshot replace the final one
+        if storage.snapshot.bgsave_in_progress {
+            log_warning!("There is a background save in progress, waiting for it to finish...");
+            storage.wait_for_bgsave().await;
+        }
+        log_notice!("Saving the final RDB snapshot before exiting.");
+        result = storage.write_rdb_file();
+    }
+    if let Some(aof) = storage.aof.as_ref() {
+        log_notice!("Calling fsync() on the AOF file.");
+        result = result.and(aof.sync());
+    }
+
+    if let Err(e) = result {
+        if !options.force {
+            log_warning!("Errors trying to shut down the server: {}", e);
+            shutting_down.send_replace(false);
+            return AppError::ShutdownFailed;
+        }
+        log_warning!("Error during forced shutdown, exiting anyway: {}", e);
+    }
+
+    if let Some(path) = &storage.config.unixsocket {
+        log_notice!("Removing the unix socket file.");
+        let _ = fs::remove_file(path);
+    }
+    log_warning!("Redis is now ready to exit, bye bye...");
+    std::process::exit(0);
+}
+
+/// Shuts down on SIGTERM or SIGINT. A failed shutdown leaves the server running.
+pub async fn handle_signals(storage: Arc<Mutex<Storage>>, info_server: Arc<Mutex<InfoServer>>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (Ok(mut terminate), Ok(mut interrupt)) = (signal(SignalKind::terminate()), signal(SignalKind::interrupt())) else {
+        log_warning!("Error installing signal handlers, SIGTERM and SIGINT will not shut down cleanly");
+        return;
+    };
+    loop {
+        let name = tokio::select! {
+            _ = terminate.recv() => "SIGTERM",
+            _ = interrupt.recv() => "SIGINT",
+        };
+        log_warning!("Received {} scheduling shutdown...", name);
+        shutdown(ShutdownOptions::default(), &storage, &info_server).await;
+        log_warning!("{} received but errors trying to shut down the server, check the logs for more information", name);
+    }
+}
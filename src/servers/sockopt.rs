@@ -0,0 +1,58 @@
+// Socket options that neither std nor tokio expose. std already links the C library on Unix,
+// so `setsockopt` is declared here rather than pulling in a bindings crate.
+
+use std::ffi::c_void;
+use std::io;
+use std::os::fd::AsRawFd;
+
+#[cfg(target_os = "linux")]
+mod consts {
+    pub const SOL_SOCKET: i32 = 1;
+    pub const SO_KEEPALIVE: i32 = 9;
+    pub const TCP_KEEPIDLE: i32 = 4;
+    pub const TCP_KEEPINTVL: i32 = 5;
+    pub const TCP_KEEPCNT: i32 = 6;
+    pub const IPV6_V6ONLY: i32 = 26;
+}
+#[cfg(target_os = "macos")]
+mod consts {
+    pub const SOL_SOCKET: i32 = 0xffff;
+    pub const SO_KEEPALIVE: i32 = 0x8;
+    pub const TCP_KEEPIDLE: i32 = 0x10;
+    pub const TCP_KEEPINTVL: i32 = 0x101;
+    pub const TCP_KEEPCNT: i32 = 0x102;
+    pub const IPV6_V6ONLY: i32 = 27;
+}
+use consts::*;
+
+const IPPROTO_TCP: i32 = 6;
+const IPPROTO_IPV6: i32 = 41;
+/// Unanswered probes after which the kernel drops a connection, as Redis configures it.
+const KEEPALIVE_PROBES: i32 = 3;
+
+extern "C" {
+    fn setsockopt(fd: i32, level: i32, name: i32, value: *const c_void, len: u32) -> i32;
+}
+
+fn set_int(socket: &impl AsRawFd, level: i32, name: i32, value: i32) -> io::Result<()> {
+    // SAFETY: the descriptor is owned by `socket` and `value` outlives the call
+    let result = unsafe {
+        setsockopt(socket.as_raw_fd(), level, name, &value as *const i32 as *const c_void, size_of::<i32>() as u32)
+    };
+    if result == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
+
+/// Restricts an IPv6 socket to IPv6, so it can share a port with an IPv4 wildcard listener.
+pub fn set_only_v6(socket: &impl AsRawFd) -> io::Result<()> {
+    set_int(socket, IPPROTO_IPV6, IPV6_V6ONLY, 1)
+}
+
+/// Enables TCP keepalive with the first probe after `interval` seconds of silence and the
+/// rest spread so a dead peer is detected after about twice the interval, as Redis does.
+pub fn set_keepalive(socket: &impl AsRawFd, interval: u64) -> io::Result<()> {
+    let idle = interval.min(i32::MAX as u64) as i32;
+    set_int(socket, SOL_SOCKET, SO_KEEPALIVE, 1)?;
+    set_int(socket, IPPROTO_TCP, TCP_KEEPIDLE, idle)?;
+    set_int(socket, IPPROTO_TCP, TCP_KEEPINTVL, (idle / KEEPALIVE_PROBES).max(1))?;
+    set_int(socket, IPPROTO_TCP, TCP_KEEPCNT, KEEPALIVE_PROBES)
+}
@@ -1,50 +1,86 @@
-use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Read, Write};
-use std::time::{Duration, Instant};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use crate::config::server_config::ServerConfig;
 use crate::errors::app_errors::AppError;
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use crate::constants::{DEFAULT_CHANGE_THRESHOLD, DEFAULT_SNAPSHOT_PERIOD};
+use crate::constants::SNAPSHOT_RETRY_DELAY;
+use crate::enums::value::Value;
+use crate::persistence::aof::Aof;
+use crate::rdb::reader::{RdbReader, RdbSummary};
+use crate::rdb::writer::save_rdb;
+use crate::types::keyspace::Keyspace;
+use crate::types::sorted_set::SortedSet;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Item {
-    pub value: String,
+    pub value: Value,
     pub created: Instant,
     pub expires: usize,
 }
 
+impl Item {
+    fn is_expired(&self) -> bool {
+        self.expires > 0 && self.created.elapsed().as_millis() > self.expires as u128
+    }
+}
+
 #[derive(Debug)]
 pub struct Snapshot {
-    change_count: u32,
-    snapshot_change_threshold: u32,
-    pub snapshot_period_secs: u32,
-    last_snapshot_time: Instant
+    pub change_count: u64,
+    last_snapshot_time: Instant,
+    pub last_save_attempt: Instant,
+    pub last_save_unix: u64,
+    pub last_save_ok: bool,
+    pub bgsave_in_progress: bool,
+    pub bgsave_scheduled: bool,
+    /// Closed once the running background save has finished writing its file.
+    pub bgsave_writer: Option<watch::Receiver<()>>,
+}
+
+/// Keyspace counters reported in the Stats section of INFO.
+#[derive(Debug, Default)]
+pub struct KeyspaceStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub expired_keys: u64,
 }
 
 #[derive(Debug)]
 pub struct Storage {
-    pub items: HashMap<String, Item>,
-    dump_path: String,
-    pub snapshot: Snapshot
+    pub items: Keyspace,
+    /// Runtime copy of the configuration, updated by CONFIG SET.
+    pub config: ServerConfig,
+    pub snapshot: Snapshot,
+    pub aof: Option<Aof>,
+    pub stats: KeyspaceStats,
 }
 
 impl Storage {
-    pub fn new() -> Self {
+    pub fn new(config: &ServerConfig) -> Self {
         Storage {
-            items: HashMap::new(),
-            dump_path: String::from("src/dump/dump.rdb"),
+            items: Keyspace::new(),
+            config: config.clone(),
             snapshot: Snapshot {
                 change_count: 0,
-                snapshot_change_threshold: DEFAULT_CHANGE_THRESHOLD,
-                snapshot_period_secs: DEFAULT_SNAPSHOT_PERIOD,
                 last_snapshot_time: Instant::now(),
-            }
+                last_save_attempt: Instant::now(),
+                last_save_unix: unix_time_secs(),
+                last_save_ok: true,
+                bgsave_in_progress: false,
+                bgsave_scheduled: false,
+                bgsave_writer: None,
+            },
+            aof: None,
+            stats: KeyspaceStats::default(),
         }
     }
 
     pub fn set(&mut self, key: String, value: String, expires: usize) {
         let item = Item {
-            value,
+            value: Value::String(value),
             created: Instant::now(),
             expires,
         };
@@ -53,20 +89,69 @@ impl Storage {
         self.snapshot.change_count += 1;
     }
 
+    /// Looks `key` up without touching hit/miss statistics, deleting it if it has expired.
+    fn lookup(&mut self, key: &str) -> Option<&Item> {
+        if self.items.get(key)?.is_expired() {
+            self.items.remove(key);
+            self.stats.expired_keys += 1;
+            return None;
+        }
+        self.items.get(key)
+    }
+
     pub fn get(&mut self, key: &str) -> Option<&Item> {
-        let item = self.items.get(key)?;
-        let is_expired = item.expires > 0 && item.created.elapsed().as_millis() > item.expires as u128;
+        match self.lookup(key) {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        self.items.get(key)
+    }
 
-        match is_expired {
-            true => None,
-            false => Some(item),
+    pub fn get_sorted_set(&mut self, key: &str) -> Result<Option<&SortedSet>, AppError> {
+        match self.get(key) {
+            Some(Item { value: Value::SortedSet(set), .. }) => Ok(Some(set)),
+            Some(_) => Err(AppError::WrongType),
+            None => Ok(None),
         }
     }
 
+    /// Returns the sorted set stored at `key`, creating an empty one if the key is missing or expired.
+    /// Callers report what they actually modified through `add_changes`.
+    pub fn get_sorted_set_mut(&mut self, key: &str) -> Result<&mut SortedSet, AppError> {
+        if self.lookup(key).is_none() {
+            self.items.insert(key.to_string(), Item {
+                value: Value::SortedSet(SortedSet::new()),
+                created: Instant::now(),
+                expires: 0,
+            });
+        }
+
+        match self.items.get_mut(key) {
+            Some(Item { value: Value::SortedSet(set), .. }) => Ok(set),
+            _ => Err(AppError::WrongType),
+        }
+    }
+
+    /// Counts writes towards the save points.
+    pub fn add_changes(&mut self, changes: u64) {
+        self.snapshot.change_count += changes;
+    }
+
+    pub fn set_sorted_set(&mut self, key: String, set: SortedSet) {
+        let item = Item {
+            value: Value::SortedSet(set),
+            created: Instant::now(),
+            expires: 0,
+        };
+
+        self.items.insert(key, item);
+        self.snapshot.change_count += 1;
+    }
+
     pub fn del(&mut self, keys: Vec<&str>) -> u16 {
         let mut deleted_items = 0;
         for key in keys {
-            if let Some(_) = self.items.remove(key) {
+            if self.items.remove(key).is_some() {
                 deleted_items += 1;
                 self.snapshot.change_count += 1;
             }
@@ -92,92 +177,163 @@ impl Storage {
         Ok(keys)
     }
 
-    pub fn load_rdb_file(&mut self) -> Result<(), AppError> {
-        let file = File::open(&self.dump_path).map_err(|e| AppError::FileError(e))?;
-        let mut reader = BufReader::new(file);
-
-        let mut header = [0; 5];
-        reader.read_exact(&mut header).map_err(|e| AppError::FileError(e))?;
-        if &header != b"REDIS" {
-            return Err(AppError::InvalidFileFormat);
-        }
+    pub fn dump_path(&self) -> PathBuf {
+        Path::new(&self.config.dir).join(&self.config.dbfilename)
+    }
 
-        let mut version = [0; 4];
-        reader.read_exact(&mut version).map_err(AppError::FileError)?;
-        if &version != b"0006" {
-            return Err(AppError::InvalidFileFormat);
-        }
+    /// Creates the persistence directory if it does not exist yet.
+    pub fn ensure_dir(&self) -> Result<(), AppError> {
+        fs::create_dir_all(&self.config.dir).map_err(|e| AppError::DirectoryError(self.config.dir.clone(), e))
+    }
 
-        loop {
-            if reader.fill_buf().map_err(AppError::FileError)?.len() < 8 {
-                break;
-            }
+    pub fn load_rdb_file(&mut self) -> Result<RdbSummary, AppError> {
+        let file = File::open(self.dump_path()).map_err(AppError::FileError)?;
+        RdbReader::new(BufReader::new(file)).load(&mut self.items)
+    }
 
-            let created_secs = reader.read_u64::<BigEndian>().map_err(AppError::FileError)?;
-            let created = Instant::now() - Duration::from_secs(created_secs);
-            let expires = reader.read_u32::<BigEndian>().map_err(AppError::FileError)? as usize;
+    pub fn save_rdb_file(&mut self) -> Result<(), AppError> {
+        if self.snapshot.bgsave_in_progress {
+            return Err(AppError::BackgroundSaveInProgress);
+        }
+        self.write_rdb_file()
+    }
 
-            let mut key = Vec::new();
-            reader.read_until(0, &mut key).map_err(AppError::FileError)?;
-            key.pop();
-            let key = String::from_utf8(key).map_err(|_| AppError::InvalidFileFormat)?;
+    /// Waits until a running background save is done writing, after which a synchronous save
+    /// can no longer be overwritten by it. Its bookkeeping only completes once the lock is released.
+    pub async fn wait_for_bgsave(&self) {
+        if let Some(mut writer) = self.snapshot.bgsave_writer.clone() {
+            let _ = writer.changed().await;
+        }
+    }
 
-            let mut value = Vec::new();
-            reader.read_until(0, &mut value).map_err(AppError::FileError)?;
-            value.pop();
-            let value = String::from_utf8(value).map_err(|_| AppError::InvalidFileFormat)?;
+    /// Saves synchronously regardless of a background save, which must have finished writing.
+    pub fn write_rdb_file(&mut self) -> Result<(), AppError> {
+        let changes = self.snapshot.change_count;
+        self.snapshot.last_save_attempt = Instant::now();
+        let result = self.ensure_dir().and_then(|_| save_rdb(&self.dump_path(), &self.items, self.config.rdbcompression));
+        self.record_save(&result, changes);
+        result
+    }
 
-            self.items.insert(key, Item { value, created, expires });
+    /// Updates persistence bookkeeping once a save covering `changes_saved` writes has finished.
+    pub fn record_save(&mut self, result: &Result<(), AppError>, changes_saved: u64) {
+        self.snapshot.last_save_ok = result.is_ok();
+        if result.is_ok() {
+            self.snapshot.change_count = self.snapshot.change_count.saturating_sub(changes_saved);
+            self.snapshot.last_save_unix = unix_time_secs();
+            self.snapshot.last_snapshot_time = Instant::now();
         }
+    }
 
-        let mut eof_marker = [0; 3];
-        reader.read_exact(&mut eof_marker).map_err(AppError::FileError)?;
-        if &eof_marker != b"EOF" {
-            return Err(AppError::InvalidFileFormat);
+    /// Pushes settings changed through CONFIG SET down to the subsystems holding their own copy.
+    pub fn apply_config(&mut self) {
+        if let Some(aof) = self.aof.as_mut() {
+            aof.fsync = self.config.appendfsync;
+            aof.auto_rewrite_percentage = self.config.auto_aof_rewrite_percentage;
+            aof.auto_rewrite_min_size = self.config.auto_aof_rewrite_min_size;
         }
+    }
 
-        Ok(())
+    pub fn background_job_in_progress(&self) -> bool {
+        self.snapshot.bgsave_in_progress || self.aof.as_ref().is_some_and(|aof| aof.rewrite_in_progress)
     }
 
-    pub fn save_rdb_file(&mut self) -> Result<(), AppError> {
-        let file = File::create(&self.dump_path).map_err(|e| AppError::FileError(e))?;
-        let mut writer = BufWriter::new(file);
+    /// Logs an executed write command to the append only file, if enabled. On failure the
+    /// command stays queued for a retry and the client is told its write is not persisted.
+    pub fn propagate(&mut self, command: &str, args: &[String]) -> Result<(), AppError> {
+        let Some(aof) = self.aof.as_mut() else {
+            return Ok(());
+        };
+        aof.append(command, args).map_err(|e| {
+            log_warning!("Error writing to AOF: {}", e);
+            AppError::AofWriteFailed(e.to_string())
+        })
+    }
 
-        // Header
-        writer.write_all(b"REDIS").map_err(|e| AppError::FileError(e))?;
-        writer.write_all(b"0006").map_err(|e| AppError::FileError(e))?;
+    /// The error write commands are refused with while the AOF cannot be written.
+    pub fn write_refusal(&self) -> Option<AppError> {
+        let aof = self.aof.as_ref()?;
+        (!aof.last_write_ok).then(|| AppError::AofWriteFailed(aof.last_write_error.clone()))
+    }
 
-        for (key, item) in &self.items {
-            // Created -> Expires -> Key -> Value
-            writer.write_u64::<BigEndian>(item.created.elapsed().as_secs()).map_err(|e| AppError::FileError(e))?;
+    pub fn persistence_info(&self) -> Vec<(String, String)> {
+        let aof = self.aof.as_ref();
+        let mut fields = vec![
+            ("loading", "0".to_string()),
+            ("rdb_changes_since_last_save", self.snapshot.change_count.to_string()),
+            ("rdb_bgsave_in_progress", (self.snapshot.bgsave_in_progress as u8).to_string()),
+            ("rdb_last_save_time", self.snapshot.last_save_unix.to_string()),
+            ("rdb_last_bgsave_status", status(self.snapshot.last_save_ok)),
+            ("aof_enabled", (aof.is_some() as u8).to_string()),
+            ("aof_rewrite_in_progress", (aof.is_some_and(|aof| aof.rewrite_in_progress) as u8).to_string()),
+            ("aof_rewrite_scheduled", (aof.is_some_and(|aof| aof.rewrite_scheduled) as u8).to_string()),
+            ("aof_last_bgrewrite_status", status(aof.is_none_or(|aof| aof.last_rewrite_ok))),
+            ("aof_last_write_status", status(aof.is_none_or(|aof| aof.last_write_ok))),
+        ];
+        if let Some(aof) = aof {
+            fields.push(("aof_current_size", aof.current_size.to_string()));
+            fields.push(("aof_base_size", aof.base_size.to_string()));
+        }
+        fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+    }
 
-            writer.write_u32::<BigEndian>(item.expires as u32).map_err(|e| AppError::FileError(e))?;
+    /// Approximate number of bytes used by keys and values.
+    pub fn used_memory(&self) -> u64 {
+        self.items.iter().map(|(key, item)| (key.len() + item.value.estimated_size()) as u64).sum()
+    }
 
-            writer.write_all(key.as_bytes()).map_err(|e| AppError::FileError(e))?;
-            writer.write_all(&[0]).map_err(|e| AppError::FileError(e))?;
+    pub fn keyspace_info(&self) -> Vec<(String, String)> {
+        let (mut keys, mut expires, mut ttl_total) = (0u64, 0u64, 0u128);
+        for (_, item) in self.items.iter() {
+            // Read the clock once so the expiry check and the TTL agree.
+            let elapsed = item.created.elapsed().as_millis();
+            if item.expires > 0 && elapsed > item.expires as u128 {
+                continue;
+            }
+            keys += 1;
+            if item.expires > 0 {
+                expires += 1;
+                ttl_total += (item.expires as u128).saturating_sub(elapsed);
+            }
+        }
 
-            writer.write_all(item.value.as_bytes()).map_err(|e| AppError::FileError(e))?;
-            writer.write_all(&[0]).map_err(|e| AppError::FileError(e))?;
+        if keys == 0 {
+            return Vec::new();
         }
+        let avg_ttl = if expires > 0 { ttl_total / expires as u128 } else { 0 };
+        vec![("db0".to_string(), format!("keys={},expires={},avg_ttl={}", keys, expires, avg_ttl))]
+    }
 
-        // End of file
-        writer.write_all(b"EOF").map_err(AppError::FileError)?;
+    /// Whether any configured save point is satisfied. After a failed save, retries wait a few seconds.
+    pub fn should_take_snapshot(&self) -> bool {
+        let since_last_save = self.snapshot.last_snapshot_time.elapsed();
+        let can_retry = self.snapshot.last_save_ok
+            || self.snapshot.last_save_attempt.elapsed() >= Duration::from_secs(SNAPSHOT_RETRY_DELAY);
 
-        Ok(())
+        can_retry && self.config.save_points.iter().any(|point| {
+            self.snapshot.change_count >= point.changes && since_last_save >= Duration::from_secs(point.seconds)
+        })
     }
+}
 
-    pub fn should_take_snapshot(&mut self) -> bool {
-        if self.snapshot.change_count > self.snapshot.snapshot_change_threshold &&
-            self.snapshot.last_snapshot_time.elapsed() >= Duration::from_secs(self.snapshot.snapshot_period_secs as u64) {
-            self.snapshot.change_count = 0;
-            return true;
-        }
-        false
-    }
+fn status(ok: bool) -> String {
+    if ok { "ok" } else { "err" }.to_string()
+}
+
+pub fn unix_time_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+pub fn unix_time_micros() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64
+}
+
+pub fn unix_time_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
 }
 
 impl Default for Storage {
     fn default() -> Self {
-        Storage::new()
+        Storage::new(&ServerConfig::default())
     }
 }
\ No newline at end of file
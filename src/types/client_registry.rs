@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use crate::config::buffer_limits::{ClientClass, OutputBufferLimits};
+use crate::config::server_config::ServerConfig;
+use crate::resp::handler::BufferLimits;
+use tokio::sync::watch;
+
+/// Which commands a CLIENT PAUSE holds back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseMode {
+    Write,
+    All,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClientPause {
+    pub until: Instant,
+    pub mode: PauseMode,
+}
+
+impl ClientPause {
+    /// Whether a command, writing or not, has to wait for this pause to end.
+    pub fn holds(&self, is_write: bool) -> bool {
+        Instant::now() < self.until && (self.mode == PauseMode::All || is_write)
+    }
+}
+
+#[derive(Debug)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub addr: String,
+    pub laddr: String,
+    pub name: String,
+    pub user: String,
+    pub created: Instant,
+    pub last_interaction: Instant,
+    pub last_command: String,
+    pub query_buffer: usize,
+    pub query_buffer_capacity: usize,
+    /// Bytes of replies not written out yet.
+    pub output_buffer: usize,
+    pub monitor: bool,
+    pub replica: bool,
+    /// Waiting for a CLIENT PAUSE to end before running its command.
+    pub blocked: bool,
+    pub no_evict: bool,
+    kill: watch::Sender<bool>,
+}
+
+impl ClientInfo {
+    pub fn client_type(&self) -> &'static str {
+        if self.replica { "replica" } else { "normal" }
+    }
+
+    pub fn class(&self) -> ClientClass {
+        if self.replica { ClientClass::Replica } else { ClientClass::Normal }
+    }
+
+    fn flags(&self) -> String {
+        let mut flags = String::new();
+        if self.monitor {
+            flags.push('O');
+        }
+        if self.replica {
+            flags.push('S');
+        }
+        if self.blocked {
+            flags.push('b');
+        }
+        if self.no_evict {
+            flags.push('e');
+        }
+        if flags.is_empty() {
+            flags.push('N');
+        }
+        flags
+    }
+
+    /// One line of CLIENT LIST / CLIENT INFO output.
+    pub fn describe(&self) -> String {
+        let now = Instant::now();
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} flags={} db=0 sub=0 psub=0 multi=-1 qbuf={} qbuf-free={} obl=0 oll=0 omem={} cmd={} user={} resp=2",
+            self.id,
+            self.addr,
+            self.laddr,
+            self.name,
+            now.duration_since(self.created).as_secs(),
+            now.duration_since(self.last_interaction).as_secs(),
+            self.flags(),
+            self.query_buffer,
+            self.query_buffer_capacity.saturating_sub(self.query_buffer),
+            self.output_buffer,
+            if self.last_command.is_empty() { "NULL" } else { &self.last_command },
+            self.user,
+        )
+    }
+
+    pub fn kill(&self) {
+        self.kill.send_replace(true);
+    }
+}
+
+/// What a connection gets back when it registers: its id, a signal fired by CLIENT KILL
+/// and a view of the current CLIENT PAUSE.
+pub struct ClientHandle {
+    pub id: u64,
+    pub killed: watch::Receiver<bool>,
+    pub pause: watch::Receiver<Option<ClientPause>>,
+}
+
+/// Every connected client, keyed by id.
+#[derive(Debug)]
+pub struct ClientRegistry {
+    clients: BTreeMap<u64, ClientInfo>,
+    next_id: u64,
+    pause: watch::Sender<Option<ClientPause>>,
+    max_clients: u64,
+    /// How long a client may stay idle, `None` when clients are never timed out.
+    idle_timeout: Option<Duration>,
+    output_limits: OutputBufferLimits,
+    query_buffer_limit: u64,
+    max_bulk_len: u64,
+}
+
+impl ClientRegistry {
+    pub fn new() -> Self {
+        ClientRegistry {
+            clients: BTreeMap::new(),
+            next_id: 1,
+            pause: watch::channel(None).0,
+            max_clients: u64::MAX,
+            idle_timeout: None,
+            output_limits: OutputBufferLimits::default(),
+            query_buffer_limit: 0,
+            max_bulk_len: 0,
+        }
+    }
+
+    pub fn configure(&mut self, config: &ServerConfig) {
+        self.max_clients = config.maxclients;
+        self.idle_timeout = (config.timeout > 0).then(|| Duration::from_secs(config.timeout));
+        self.output_limits = config.client_output_buffer_limits;
+        self.query_buffer_limit = config.client_query_buffer_limit;
+        self.max_bulk_len = config.proto_max_bulk_len;
+    }
+
+    /// The buffer limits connections of `class` currently have to keep to.
+    pub fn buffer_limits(&self, class: ClientClass) -> BufferLimits {
+        BufferLimits {
+            output: self.output_limits.get(class),
+            query_buffer: self.query_buffer_limit,
+            max_bulk_len: self.max_bulk_len,
+        }
+    }
+
+    pub fn max_clients(&self) -> u64 {
+        self.max_clients
+    }
+
+    /// Whether a new connection would go over `maxclients`.
+    pub fn is_full(&self) -> bool {
+        self.clients.len() as u64 >= self.max_clients
+    }
+
+    /// Disconnects clients idle for longer than `timeout`, returning how many. MONITOR,
+    /// replica and blocked connections are never idle in that sense, and nobody is timed out
+    /// while a CLIENT PAUSE may be holding commands back.
+    pub fn kill_idle(&self) -> usize {
+        let Some(timeout) = self.idle_timeout else {
+            return 0;
+        };
+        if self.pause.borrow().is_some_and(|pause| Instant::now() < pause.until) {
+            return 0;
+        }
+        let idle = self
+            .clients
+            .values()
+            .filter(|client| !client.monitor && !client.replica && !client.blocked && client.last_interaction.elapsed() > timeout);
+        let mut count = 0;
+        for client in idle {
+            client.kill();
+            count += 1;
+        }
+        count
+    }
+
+    pub fn register(&mut self, addr: String, laddr: String) -> ClientHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+        let (kill, killed) = watch::channel(false);
+        let now = Instant::now();
+
+        self.clients.insert(id, ClientInfo {
+            id,
+            addr,
+            laddr,
+            name: String::new(),
+            user: "default".to_string(),
+            created: now,
+            last_interaction: now,
+            last_command: String::new(),
+            query_buffer: 0,
+            query_buffer_capacity: 0,
+            output_buffer: 0,
+            monitor: false,
+            replica: false,
+            blocked: false,
+            no_evict: false,
+            kill,
+        });
+        ClientHandle { id, killed, pause: self.pause.subscribe() }
+    }
+
+    pub fn unregister(&mut self, id: u64) {
+        self.clients.remove(&id);
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ClientInfo> {
+        self.clients.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut ClientInfo> {
+        self.clients.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ClientInfo> {
+        self.clients.values()
+    }
+
+    pub fn pause(&mut self, until: Instant, mode: PauseMode) {
+        // A new pause never shortens or weakens one that is already in effect
+        let pause = match *self.pause.borrow() {
+            Some(current) if Instant::now() < current.until => ClientPause {
+                until: until.max(current.until),
+                mode: if current.mode == PauseMode::All { PauseMode::All } else { mode },
+            },
+            _ => ClientPause { until, mode },
+        };
+        self.pause.send_replace(Some(pause));
+    }
+
+    pub fn unpause(&mut self) {
+        self.pause.send_replace(None);
+    }
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        ClientRegistry::new()
+    }
+}
+
+/// Resolves once CLIENT KILL fired for the connection owning `killed`, or the client was
+/// unregistered. The borrowed value is dropped here so callers stay `Send` across awaits.
+pub async fn until_killed(killed: &mut watch::Receiver<bool>) {
+    let _ = killed.wait_for(|killed| *killed).await;
+}
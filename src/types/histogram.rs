@@ -0,0 +1,117 @@
+/// Values below this are stored exactly; above it each power of two is split into
+/// `SUB_BUCKETS / 2` linear buckets, keeping the relative error under 1.6%.
+const SUB_BUCKETS: u64 = 128;
+const HALF_SUB_BUCKETS: u64 = SUB_BUCKETS / 2;
+
+/// Log-linear histogram in the spirit of HdrHistogram, growing its buckets on demand.
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+}
+
+impl Histogram {
+    fn bucket_index(value: u64) -> usize {
+        if value < SUB_BUCKETS {
+            return value as usize;
+        }
+        let shift = (u64::BITS - value.leading_zeros()) as u64 - 7;
+        (SUB_BUCKETS + (shift - 1) * HALF_SUB_BUCKETS + ((value >> shift) - HALF_SUB_BUCKETS)) as usize
+    }
+
+    /// Highest value that falls into the bucket at `index`.
+    fn bucket_upper_bound(index: usize) -> u64 {
+        let index = index as u64;
+        if index < SUB_BUCKETS {
+            return index;
+        }
+        let shift = (index - SUB_BUCKETS) / HALF_SUB_BUCKETS + 1;
+        let sub_bucket = (index - SUB_BUCKETS) % HALF_SUB_BUCKETS + HALF_SUB_BUCKETS;
+        (((sub_bucket as u128 + 1) << shift) - 1).min(u64::MAX as u128) as u64
+    }
+
+    pub fn record(&mut self, value: u64) {
+        let index = Self::bucket_index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        self.total += 1;
+    }
+
+    /// Value at or below which `percentile` percent of the recorded values fall.
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        // Multiplying first keeps exact products such as 99.9% of 1000 from rounding up past 999
+        let target = ((percentile * self.total as f64 / 100.0).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return Self::bucket_upper_bound(index);
+            }
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket_of(value: u64) -> (usize, u64) {
+        let index = Histogram::bucket_index(value);
+        (index, Histogram::bucket_upper_bound(index))
+    }
+
+    #[test]
+    fn places_boundary_values() {
+        assert_eq!(bucket_of(0), (0, 0));
+        assert_eq!(bucket_of(127), (127, 127));
+        assert_eq!(bucket_of(128), (128, 129));
+        assert_eq!(bucket_of(129), (128, 129));
+        assert_eq!(bucket_of(255), (191, 255));
+        assert_eq!(bucket_of(256), (192, 259));
+        assert_eq!(bucket_of(u64::MAX), (3775, u64::MAX));
+    }
+
+    #[test]
+    fn keeps_the_relative_error_bounded() {
+        let mut previous = 0;
+        for value in [1, 127, 128, 200, 255, 256, 1000, 65_535, 1 << 40, u64::MAX / 3, u64::MAX - 1, u64::MAX] {
+            let (index, upper) = bucket_of(value);
+            assert!(index >= previous, "buckets must not decrease at {}", value);
+            assert!(upper >= value);
+            assert!((upper - value) as f64 <= value as f64 / HALF_SUB_BUCKETS as f64, "error too large at {}", value);
+            previous = index;
+        }
+    }
+
+    #[test]
+    fn reports_percentiles_of_a_known_distribution() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.percentile(50.0), 0);
+
+        for value in 1..=100 {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.percentile(0.0), 1);
+        assert_eq!(histogram.percentile(50.0), 50);
+        assert_eq!(histogram.percentile(99.0), 99);
+        assert_eq!(histogram.percentile(100.0), 100);
+
+        let mut histogram = Histogram::default();
+        for value in 1..=1000 {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.percentile(50.0), 503);
+        assert_eq!(histogram.percentile(99.9), 999);
+        assert_eq!(histogram.percentile(100.0), 1007);
+    }
+
+    #[test]
+    fn records_the_largest_value() {
+        let mut histogram = Histogram::default();
+        histogram.record(u64::MAX);
+        assert_eq!(histogram.percentile(100.0), u64::MAX);
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use crate::storage::Item;
+
+const SHARD_COUNT: usize = 1024;
+
+/// Key/value map split into reference-counted shards.
+///
+/// Cloning a keyspace only clones the shard pointers, which gives background saves a cheap
+/// point-in-time view. A shard is copied the first time it is written while a clone is alive,
+/// so the cost of a snapshot is proportional to how much of the dataset changes during it.
+#[derive(Debug, Clone)]
+pub struct Keyspace {
+    shards: Vec<Arc<HashMap<String, Item>>>,
+}
+
+impl Keyspace {
+    pub fn new() -> Self {
+        Keyspace {
+            shards: (0..SHARD_COUNT).map(|_| Arc::new(HashMap::new())).collect(),
+        }
+    }
+
+    fn shard_index(key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish() as usize % SHARD_COUNT
+    }
+
+    fn shard_mut(&mut self, key: &str) -> &mut HashMap<String, Item> {
+        Arc::make_mut(&mut self.shards[Self::shard_index(key)])
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        self.shards[Self::shard_index(key)].get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Item> {
+        // Avoid copying a shared shard when the key is not there
+        self.get(key)?;
+        self.shard_mut(key).get_mut(key)
+    }
+
+    pub fn insert(&mut self, key: String, item: Item) -> Option<Item> {
+        self.shard_mut(&key).insert(key, item)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Item> {
+        self.get(key)?;
+        self.shard_mut(key).remove(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Item)> {
+        self.shards.iter().flat_map(|shard| shard.iter())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.iter().map(|(key, _)| key)
+    }
+}
+
+impl Default for Keyspace {
+    fn default() -> Self {
+        Keyspace::new()
+    }
+}
@@ -0,0 +1,5 @@
+pub mod sorted_set;
+pub mod keyspace;
+pub mod histogram;
+pub mod slow_log;
+pub mod client_registry;
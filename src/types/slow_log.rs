@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+use crate::config::server_config::ServerConfig;
+use crate::constants::{SLOWLOG_ENTRY_MAX_ARGC, SLOWLOG_ENTRY_MAX_STRING};
+use crate::storage::unix_time_secs;
+
+#[derive(Debug, Clone)]
+pub struct SlowLogEntry {
+    pub id: u64,
+    pub timestamp: u64,
+    /// Execution time in microseconds.
+    pub duration: u64,
+    pub args: Vec<String>,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+/// Bounded log of the most recent commands slower than `slowlog-log-slower-than`, newest first.
+#[derive(Debug)]
+pub struct SlowLog {
+    entries: VecDeque<SlowLogEntry>,
+    next_id: u64,
+    log_slower_than: i64,
+    max_len: usize,
+}
+
+impl SlowLog {
+    pub fn new(config: &ServerConfig) -> Self {
+        SlowLog {
+            entries: VecDeque::new(),
+            next_id: 0,
+            log_slower_than: config.slowlog_log_slower_than,
+            max_len: config.slowlog_max_len as usize,
+        }
+    }
+
+    pub fn configure(&mut self, config: &ServerConfig) {
+        self.log_slower_than = config.slowlog_log_slower_than;
+        self.max_len = config.slowlog_max_len as usize;
+        self.entries.truncate(self.max_len);
+    }
+
+    /// Logs the command if it took at least the configured threshold. A negative threshold disables the log.
+    pub fn record(&mut self, command: &str, args: &[String], duration: u64, client_addr: &str, client_name: &str) {
+        if self.log_slower_than < 0 || duration < self.log_slower_than as u64 {
+            return;
+        }
+
+        let argc = args.len() + 1;
+        let mut logged: Vec<String> = std::iter::once(command)
+            .chain(args.iter().map(String::as_str))
+            .take(if argc > SLOWLOG_ENTRY_MAX_ARGC { SLOWLOG_ENTRY_MAX_ARGC - 1 } else { argc })
+            .map(truncate_arg)
+            .collect();
+        if argc > SLOWLOG_ENTRY_MAX_ARGC {
+            logged.push(format!("... ({} more arguments)", argc - SLOWLOG_ENTRY_MAX_ARGC + 1));
+        }
+
+        self.entries.push_front(SlowLogEntry {
+            id: self.next_id,
+            timestamp: unix_time_secs(),
+            duration,
+            args: logged,
+            client_addr: client_addr.to_string(),
+            client_name: client_name.to_string(),
+        });
+        self.next_id += 1;
+        self.entries.truncate(self.max_len);
+    }
+
+    /// Up to `count` entries, newest first. `None` returns all of them.
+    pub fn get(&self, count: Option<usize>) -> impl Iterator<Item = &SlowLogEntry> {
+        self.entries.iter().take(count.unwrap_or(usize::MAX))
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn truncate_arg(arg: &str) -> String {
+    if arg.len() <= SLOWLOG_ENTRY_MAX_STRING {
+        return arg.to_string();
+    }
+    let mut end = SLOWLOG_ENTRY_MAX_STRING;
+    while !arg.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... ({} more bytes)", &arg[..end], arg.len() - end)
+}
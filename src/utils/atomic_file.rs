@@ -0,0 +1,44 @@
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::errors::app_errors::AppError;
+
+/// Writes `path` through a temporary file in the same directory that is fsynced and then renamed
+/// over the target, so a crash mid-write never leaves a truncated file behind.
+pub fn write_atomically<F>(path: &Path, write: F) -> Result<(), AppError>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), AppError>,
+{
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let temp_path = dir.join(format!("temp-{}-{}", std::process::id(), file_name));
+
+    let result = (|| {
+        let file = File::create(&temp_path).map_err(AppError::FileError)?;
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush().map_err(AppError::FileError)?;
+        writer.get_ref().sync_all().map_err(AppError::FileError)?;
+        drop(writer);
+
+        fs::rename(&temp_path, path).map_err(AppError::FileError)?;
+        sync_dir(dir)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Persists the directory entry created by a rename.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<(), AppError> {
+    File::open(dir).and_then(|d| d.sync_all()).map_err(AppError::FileError)
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<(), AppError> {
+    Ok(())
+}
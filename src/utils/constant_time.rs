@@ -0,0 +1,9 @@
+/// Compares two byte strings in time that depends only on their lengths, never on where they
+/// first differ, so password checks do not leak how much of a guess was right.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = (a.len() != b.len()) as u8;
+    for i in 0..a.len().max(b.len()) {
+        diff |= a.get(i).copied().unwrap_or_default() ^ b.get(i).copied().unwrap_or_default();
+    }
+    diff == 0
+}
@@ -0,0 +1,133 @@
+/// Matches `text` against a Redis style glob pattern supporting `*`, `?`, `[...]` classes
+/// (with `^` negation and `a-z` ranges) and `\` escapes.
+pub fn glob_match(pattern: &str, text: &str, nocase: bool) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last `*` and the text position it is currently assumed to stop at
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if pattern.get(p) == Some(&'*') {
+            while pattern.get(p) == Some(&'*') {
+                p += 1;
+            }
+            if p == pattern.len() {
+                return true;
+            }
+            star = Some((p, t));
+            continue;
+        }
+
+        if let Some(next) = match_token(&pattern, p, text[t], nocase) {
+            p = next;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` swallow one more character and retry; earlier stars never need to change
+            star = Some((star_p, star_t + 1));
+            p = star_p;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn chars_equal(a: char, b: char, nocase: bool) -> bool {
+    if nocase { a.eq_ignore_ascii_case(&b) } else { a == b }
+}
+
+/// Matches the single-character token at `pattern[p]` against `c`, returning where the next token starts.
+fn match_token(pattern: &[char], mut p: usize, c: char, nocase: bool) -> Option<usize> {
+    match *pattern.get(p)? {
+        '?' => Some(p + 1),
+        '[' => {
+            p += 1;
+            let negate = pattern.get(p) == Some(&'^');
+            if negate {
+                p += 1;
+            }
+
+            let mut matched = false;
+            while p < pattern.len() && pattern[p] != ']' {
+                if pattern[p] == '\\' && p + 1 < pattern.len() {
+                    p += 1;
+                    matched |= chars_equal(pattern[p], c, nocase);
+                } else if p + 2 < pattern.len() && pattern[p + 1] == '-' && pattern[p + 2] != ']' {
+                    let (mut start, mut end) = (pattern[p], pattern[p + 2]);
+                    if start > end {
+                        std::mem::swap(&mut start, &mut end);
+                    }
+                    let c = if nocase { c.to_ascii_lowercase() } else { c };
+                    let (start, end) = if nocase {
+                        (start.to_ascii_lowercase(), end.to_ascii_lowercase())
+                    } else {
+                        (start, end)
+                    };
+                    matched |= start <= c && c <= end;
+                    p += 2;
+                } else {
+                    matched |= chars_equal(pattern[p], c, nocase);
+                }
+                p += 1;
+            }
+            (matched != negate).then_some((p + 1).min(pattern.len()))
+        }
+        '\\' if p + 1 < pattern.len() => chars_equal(pattern[p + 1], c, nocase).then_some(p + 2),
+        literal => chars_equal(literal, c, nocase).then_some(p + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literals_and_wildcards() {
+        assert!(glob_match("hello", "hello", false));
+        assert!(!glob_match("hello", "hell", false));
+        assert!(glob_match("h?llo", "hallo", false));
+        assert!(!glob_match("h?llo", "hllo", false));
+        assert!(glob_match("h*llo", "hllo", false));
+        assert!(glob_match("h*llo", "heeeello", false));
+        assert!(glob_match("*", "", false));
+        assert!(glob_match("**", "anything", false));
+        assert!(!glob_match("", "a", false));
+        assert!(glob_match("a*b*c", "aXbYbZc", false));
+        assert!(!glob_match("a*b*c", "aXbYbZ", false));
+        assert!(glob_match("*.log", "server.log.log", false));
+    }
+
+    #[test]
+    fn matches_classes() {
+        assert!(glob_match("h[ae]llo", "hello", false));
+        assert!(!glob_match("h[ae]llo", "hillo", false));
+        assert!(glob_match("h[^e]llo", "hallo", false));
+        assert!(!glob_match("h[^e]llo", "hello", false));
+        assert!(glob_match("h[a-b]llo", "hbllo", false));
+        assert!(glob_match("h[b-a]llo", "hallo", false));
+        assert!(!glob_match("h[a-b]llo", "hcllo", false));
+        assert!(glob_match("[\\]]", "]", false));
+        assert!(glob_match("*[0-9]", "key42", false));
+    }
+
+    #[test]
+    fn honors_escapes_and_case() {
+        assert!(glob_match("h\\*llo", "h*llo", false));
+        assert!(!glob_match("h\\*llo", "hello", false));
+        assert!(glob_match("trailing\\", "trailing\\", false));
+        assert!(glob_match("HeL?O", "hello", true));
+        assert!(!glob_match("HeL?O", "hello", false));
+        assert!(glob_match("[A-C]x", "bX", true));
+    }
+
+    #[test]
+    fn does_not_backtrack_exponentially() {
+        let text = "a".repeat(10_000);
+        let pattern = format!("{}b", "a*".repeat(50));
+        assert!(!glob_match(&pattern, &text, false));
+        assert!(glob_match(&format!("{}a", "a*".repeat(50)), &text, false));
+    }
+}
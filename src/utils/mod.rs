@@ -0,0 +1,6 @@
+pub mod atomic_file;
+pub mod units;
+pub mod glob;
+pub mod repr;
+pub mod constant_time;
+pub mod sha256;